@@ -0,0 +1,262 @@
+//! Command-line tool printing the compute environments detected on the current machine.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use apollo_environment_detector::{
+    Candidate, DetectionSnapshot, EnvironmentDetector, ImdsConfig, MAX_INDIVIDUAL_WEIGHTING,
+};
+
+const USAGE: &str = "\
+Print the compute environments detected on the current machine.
+
+Usage: environment-detector [OPTIONS]
+
+Options:
+  -t, --threshold <THRESHOLD>  Minimum score for a candidate [default: 16384]
+      --all                    Also print candidates below the threshold
+      --json                   Print the full detection report as JSON
+      --snapshot               Print the detection inputs as JSON instead of detecting
+      --replay <FILE>          Detect from a snapshot file instead of the current machine
+      --imds                   Also query the instance metadata services of the cloud providers
+  -h, --help                   Print help
+  -V, --version                Print version";
+
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Detect(Options),
+    Snapshot { imds: bool },
+    Help,
+    Version,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Options {
+    threshold: u16,
+    all: bool,
+    json: bool,
+    replay: Option<PathBuf>,
+    imds: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            threshold: MAX_INDIVIDUAL_WEIGHTING,
+            all: false,
+            json: false,
+            replay: None,
+            imds: false,
+        }
+    }
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut options = Options::default();
+    let mut snapshot = false;
+    let mut imds = false;
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name.to_string(), Some(value.to_string())),
+            None => (arg, None),
+        };
+
+        match name.as_str() {
+            "-t" | "--threshold" => {
+                let value = value
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("missing value for `{name}`"))?;
+                options.threshold = value
+                    .parse()
+                    .map_err(|_| format!("invalid threshold `{value}`"))?;
+            }
+            "--all" => options.all = true,
+            "--json" => options.json = true,
+            "--snapshot" => snapshot = true,
+            "--imds" => imds = true,
+            "--replay" => {
+                let value = value
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("missing value for `{name}`"))?;
+                options.replay = Some(PathBuf::from(value));
+            }
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            _ => return Err(format!("unexpected argument `{name}`")),
+        }
+    }
+
+    if imds && options.replay.is_some() {
+        return Err("`--imds` cannot be used with `--replay`".to_string());
+    }
+    if snapshot {
+        if options.replay.is_some() {
+            return Err("`--snapshot` cannot be used with `--replay`".to_string());
+        }
+        return Ok(Command::Snapshot { imds });
+    }
+    options.imds = imds;
+
+    Ok(Command::Detect(options))
+}
+
+fn read_snapshot(path: &Path) -> Result<DetectionSnapshot, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| format!("failed to read `{}`: {err}", path.display()))?;
+    serde_json::from_str(&content)
+        .map_err(|err| format!("invalid snapshot `{}`: {err}", path.display()))
+}
+
+fn detector(imds: bool) -> EnvironmentDetector {
+    let detector = EnvironmentDetector::new();
+    if imds {
+        detector.with_imds(ImdsConfig::new())
+    } else {
+        detector
+    }
+}
+
+fn print_candidates(candidates: &[Candidate]) {
+    for candidate in candidates {
+        println!(
+            "{:>5}  {:>6}  {:>8}  {:>8}  {:>7}  {} ({})",
+            candidate.score,
+            candidate.smbios.score,
+            candidate.env_vars.score,
+            candidate.signals.platform_score,
+            candidate.signals.runtime_score,
+            candidate.environment,
+            candidate.environment.id(),
+        );
+        if !candidate.exclusions.is_empty() {
+            println!("       excluded by {}", candidate.exclusions.join(", "));
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(Command::Detect(options)) => options,
+        Ok(Command::Snapshot { imds }) => {
+            return match serde_json::to_string_pretty(&detector(imds).snapshot()) {
+                Ok(json) => {
+                    println!("{json}");
+                    ExitCode::SUCCESS
+                }
+                Err(err) => {
+                    eprintln!("error: {err}");
+                    ExitCode::FAILURE
+                }
+            };
+        }
+        Ok(Command::Help) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Ok(Command::Version) => {
+            println!("environment-detector {}", env!("CARGO_PKG_VERSION"));
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let detector = detector(options.imds);
+    let report = match &options.replay {
+        Some(path) => match read_snapshot(path) {
+            Ok(snapshot) => detector.detect_snapshot(&snapshot, options.threshold),
+            Err(err) => {
+                eprintln!("error: {err}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => detector.detect_report(options.threshold),
+    };
+
+    if options.json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{json}"),
+            Err(err) => {
+                eprintln!("error: {err}");
+                return ExitCode::FAILURE;
+            }
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    println!("Threshold: {}", report.threshold);
+    for signal in &report.signals {
+        println!("Signal: {signal}");
+    }
+    println!();
+    println!("SCORE  SMBIOS  ENV VARS  PLATFORM  RUNTIME  ENVIRONMENT");
+    if report.candidates.is_empty() {
+        println!("No environment detected");
+    }
+    print_candidates(&report.candidates);
+
+    if options.all && !report.rejected.is_empty() {
+        println!("--- below threshold ---");
+        print_candidates(&report.rejected);
+    }
+
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case::default(&[], Ok(Command::Detect(Options::default())))]
+    #[case::threshold(
+        &["--threshold", "100"],
+        Ok(Command::Detect(Options { threshold: 100, ..Default::default() }))
+    )]
+    #[case::threshold_equals(
+        &["--threshold=100"],
+        Ok(Command::Detect(Options { threshold: 100, ..Default::default() }))
+    )]
+    #[case::threshold_short(
+        &["-t", "0", "--all", "--json"],
+        Ok(Command::Detect(Options { threshold: 0, all: true, json: true, ..Default::default() }))
+    )]
+    #[case::replay(
+        &["--replay", "snapshot.json"],
+        Ok(Command::Detect(Options { replay: Some("snapshot.json".into()), ..Default::default() }))
+    )]
+    #[case::imds(
+        &["--imds"],
+        Ok(Command::Detect(Options { imds: true, ..Default::default() }))
+    )]
+    #[case::imds_replay(
+        &["--imds", "--replay=snapshot.json"],
+        Err("`--imds` cannot be used with `--replay`".to_string())
+    )]
+    #[case::snapshot(&["--snapshot"], Ok(Command::Snapshot { imds: false }))]
+    #[case::snapshot_imds(&["--snapshot", "--imds"], Ok(Command::Snapshot { imds: true }))]
+    #[case::snapshot_replay(
+        &["--snapshot", "--replay=snapshot.json"],
+        Err("`--snapshot` cannot be used with `--replay`".to_string())
+    )]
+    #[case::missing_replay(&["--replay"], Err("missing value for `--replay`".to_string()))]
+    #[case::help(&["--all", "-h"], Ok(Command::Help))]
+    #[case::version(&["--version"], Ok(Command::Version))]
+    #[case::missing_threshold(&["-t"], Err("missing value for `-t`".to_string()))]
+    #[case::invalid_threshold(&["-t", "65536"], Err("invalid threshold `65536`".to_string()))]
+    #[case::unexpected(&["--verbose"], Err("unexpected argument `--verbose`".to_string()))]
+    fn test_parse_args(#[case] args: &[&str], #[case] expected: Result<Command, String>) {
+        let result = parse_args(args.iter().map(ToString::to_string));
+
+        assert_eq!(expected, result);
+    }
+}
@@ -0,0 +1,94 @@
+use std::{
+    collections::HashMap,
+    sync::{OnceLock, PoisonError, RwLock},
+};
+
+use crate::{DetectionReport, DetectionSnapshot, EnvironmentDetector};
+
+static CACHE: OnceLock<RwLock<Cache>> = OnceLock::new();
+
+/// Inputs read from the current host, and the reports scored from them for each threshold.
+#[derive(Default)]
+struct Cache {
+    snapshot: Option<DetectionSnapshot>,
+    reports: HashMap<u16, &'static DetectionReport>,
+}
+
+/// Detect potential [`ComputeEnvironment`](crate::ComputeEnvironment)s above a certain weighted
+/// threshold, computed once for the lifetime of the process.
+///
+/// The first call reads the environment variables and system files as [`detect_report`] does.
+/// The report of each threshold is then computed once, and the same `&'static` reference is
+/// returned by later calls with that threshold, until [`refresh`] is called.
+///
+/// Cached reports are never freed, so that the references remain valid after a refresh. This
+/// keeps one report per distinct threshold, for each refresh.
+///
+/// [`detect_report`]: crate::detect_report
+///
+/// # Examples
+///
+/// ```
+/// use apollo_environment_detector::{detect_cached, MAX_INDIVIDUAL_WEIGHTING};
+///
+/// let report = detect_cached(MAX_INDIVIDUAL_WEIGHTING);
+/// println!("{:?}", report.best());
+/// ```
+pub fn detect_cached(threshold: u16) -> &'static DetectionReport {
+    let cache = CACHE.get_or_init(Default::default);
+    if let Some(report) = cache
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .reports
+        .get(&threshold)
+    {
+        return report;
+    }
+
+    let mut cache = cache.write().unwrap_or_else(PoisonError::into_inner);
+    let Cache { snapshot, reports } = &mut *cache;
+    // Another thread may have computed the report while waiting for the lock.
+    reports.entry(threshold).or_insert_with(|| {
+        let snapshot = snapshot.get_or_insert_with(DetectionSnapshot::capture);
+        Box::leak(Box::new(
+            EnvironmentDetector::new().detect_snapshot(snapshot, threshold),
+        ))
+    })
+}
+
+/// Clear the results cached by [`detect_cached`], so that the next call reads the environment
+/// variables and system files again.
+///
+/// References returned before remain valid, but are not updated. This should only be called when
+/// the environment is expected to have changed, and not on a hot path.
+pub fn refresh() {
+    if let Some(cache) = CACHE.get() {
+        *cache.write().unwrap_or_else(PoisonError::into_inner) = Cache::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use crate::MAX_INDIVIDUAL_WEIGHTING;
+
+    use super::{detect_cached, refresh};
+
+    #[test]
+    fn test_detect_cached() {
+        let first = detect_cached(MAX_INDIVIDUAL_WEIGHTING);
+        assert_eq!(MAX_INDIVIDUAL_WEIGHTING, first.threshold);
+        assert!(ptr::eq(first, detect_cached(MAX_INDIVIDUAL_WEIGHTING)));
+
+        let other = detect_cached(u16::MIN);
+        assert_eq!(u16::MIN, other.threshold);
+        assert!(ptr::eq(first, detect_cached(MAX_INDIVIDUAL_WEIGHTING)));
+
+        refresh();
+
+        let refreshed = detect_cached(MAX_INDIVIDUAL_WEIGHTING);
+        assert!(!ptr::eq(first, refreshed));
+        assert_eq!(first, refreshed);
+    }
+}
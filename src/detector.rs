@@ -1,19 +1,47 @@
 use std::{cmp::Ordering, collections::HashSet, ops::Deref};
 
 use crate::{
-    smbios::{Smbios, SmbiosPattern},
+    exclusion::{self, Exclusion},
+    report::{Candidate, EnvVarsEvidence, SignalsEvidence},
+    signal::{self, Signal},
+    smbios::SmbiosPattern,
     specificity::{OrderingExt, Specificity},
-    ComputeEnvironment, MAX_INDIVIDUAL_WEIGHTING,
+    ComputeEnvironment, DetectionSnapshot, MAX_INDIVIDUAL_WEIGHTING,
 };
 
+/// Detector for a single [`ComputeEnvironment`], based on a [`SmbiosPattern`], a set of
+/// environment variables and optional [`Signal`]s.
+///
+/// Custom detectors can be registered through
+/// [`EnvironmentDetector::with_detector`](crate::EnvironmentDetector::with_detector), where they
+/// are scored and ranked alongside the built-in ones.
+///
+/// # Examples
+///
+/// ```
+/// use apollo_environment_detector::{ComputeEnvironment, CustomEnvironment, Detector, SmbiosPattern};
+///
+/// const ACME: Detector = Detector::new(
+///     ComputeEnvironment::Custom(CustomEnvironment::new("Acme Platform", "acme_platform")),
+///     SmbiosPattern::new().with_sys_vendor("acme"),
+///     &["ACME_NODE_ID", "ACME_REGION"],
+/// );
+/// ```
 #[derive(Debug, Clone)]
 pub struct Detector {
-    pub environment: ComputeEnvironment,
-    pub smbios: SmbiosPattern,
-    pub env_vars: &'static [&'static str],
+    pub(crate) environment: ComputeEnvironment,
+    pub(crate) smbios: SmbiosPattern,
+    pub(crate) env_vars: &'static [&'static str],
+    pub(crate) signals: &'static [Signal],
+    pub(crate) exclusions: &'static [Exclusion],
 }
 
 impl Detector {
+    /// Create a new detector.
+    ///
+    /// The environment is considered a match when both the `smbios` pattern matches and the
+    /// `env_vars` are set. An empty pattern or an empty list of environment variables gives a
+    /// neutral score for that part.
     pub const fn new(
         environment: ComputeEnvironment,
         smbios: SmbiosPattern,
@@ -23,9 +51,32 @@ impl Detector {
             environment,
             smbios,
             env_vars,
+            signals: signal::EMPTY,
+            exclusions: exclusion::EMPTY,
         }
     }
 
+    /// Expect any of the given signals.
+    ///
+    /// The SMBIOS part of the score is raised to the weight of the strongest platform signal that
+    /// was observed, if higher, and the environment variables part to the weight of the strongest
+    /// other signal. Missing signals never lower the score.
+    pub const fn with_signals(self, signals: &'static [Signal]) -> Self {
+        Self { signals, ..self }
+    }
+
+    /// Rule out this detector when any of the given exclusions is observed.
+    ///
+    /// An excluded detector has a score of 0, and is never detected whatever the threshold.
+    pub const fn with_exclusions(self, exclusions: &'static [Exclusion]) -> Self {
+        Self { exclusions, ..self }
+    }
+
+    /// [`ComputeEnvironment`] detected by this detector.
+    pub fn environment(&self) -> ComputeEnvironment {
+        self.environment
+    }
+
     /// Returns a score from 0-MAX_TOTAL_WEIGHTING representing the weight of the detected matches from
     /// SMBIOS information and environment variables.
     ///
@@ -33,25 +84,72 @@ impl Detector {
     ///   - u16::MAX = 65535, which is 2^16-1
     ///   - the combined score goes from 0-2^15, therefore each component goes to 2^14 in order
     ///     to have enough buffer compared to 2^15 to avoid thresholding and overflows.
-    pub fn detect(&self, smbios: &Smbios, env_vars: &HashSet<&'static str>) -> u16 {
-        let smbios_detect = self.smbios.detect(smbios);
+    #[cfg(test)]
+    pub(crate) fn detect(&self, snapshot: &DetectionSnapshot) -> u16 {
+        self.evaluate(snapshot).score
+    }
 
-        let env_vars_detect = if self.env_vars.is_empty() {
+    /// Returns a score from 0-MAX_TOTAL_WEIGHTING, as described for `detect`, alongside the evidence
+    /// that led to it.
+    pub(crate) fn evaluate(&self, snapshot: &DetectionSnapshot) -> Candidate {
+        let smbios = self.smbios.evaluate(&snapshot.smbios);
+
+        let (matched, missing): (Vec<&str>, Vec<&str>) = self
+            .env_vars
+            .iter()
+            .partition(|env_var| snapshot.env_vars.contains(**env_var));
+        let env_vars_score = if self.env_vars.is_empty() {
             // Half of the max individual weigh for a single detector to avoid giving too much weight
             // to empty matches.
             MAX_INDIVIDUAL_WEIGHTING / 2
         } else {
-            (self.env_vars.iter().fold(0usize, |acc, env_var| {
-                if env_vars.contains(env_var) {
-                    acc + 1
-                } else {
-                    acc
-                }
-            }) * MAX_INDIVIDUAL_WEIGHTING as usize
-                / self.env_vars.len()) as u16
+            (matched.len() * MAX_INDIVIDUAL_WEIGHTING as usize / self.env_vars.len()) as u16
+        };
+        let env_vars = EnvVarsEvidence {
+            score: env_vars_score,
+            matched: matched.into_iter().map(ToString::to_string).collect(),
+            missing: missing.into_iter().map(ToString::to_string).collect(),
+        };
+
+        let (matched, missing): (Vec<Signal>, Vec<Signal>) = self
+            .signals
+            .iter()
+            .partition(|signal| snapshot.signals.contains(*signal));
+        let score = |platform: bool| {
+            matched
+                .iter()
+                .filter(|signal| signal.is_platform() == platform)
+                .map(Signal::weight)
+                .max()
+                .unwrap_or(0)
+        };
+        let signals = SignalsEvidence {
+            platform_score: score(true),
+            runtime_score: score(false),
+            matched,
+            missing,
         };
 
-        smbios_detect + env_vars_detect
+        let exclusions: Vec<String> = self
+            .exclusions
+            .iter()
+            .filter(|exclusion| exclusion.matches(snapshot))
+            .map(ToString::to_string)
+            .collect();
+        let score = if exclusions.is_empty() {
+            smbios.score.max(signals.platform_score) + env_vars.score.max(signals.runtime_score)
+        } else {
+            0
+        };
+
+        Candidate {
+            environment: self.environment,
+            score,
+            smbios,
+            env_vars,
+            signals,
+            exclusions,
+        }
     }
 }
 
@@ -68,13 +166,13 @@ impl Specificity for Detector {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashSet;
-
     use rstest::rstest;
 
     use crate::{
-        smbios::{self, Smbios, SmbiosPattern},
-        ComputeEnvironment, MAX_INDIVIDUAL_WEIGHTING, MAX_TOTAL_WEIGHTING,
+        signal::{self, Signal},
+        smbios::{self, Smbios, SmbiosField, SmbiosPattern},
+        ComputeEnvironment, ContainerRuntime, DetectionSnapshot, Exclusion, Hypervisor,
+        MAX_INDIVIDUAL_WEIGHTING, MAX_TOTAL_WEIGHTING,
     };
 
     use super::Detector;
@@ -95,8 +193,139 @@ mod tests {
             smbios_pattern,
             &["TESTING_ENV"],
         )
-        .detect(&smbios, &HashSet::from_iter(env_vars.iter().cloned()));
+        .detect(&DetectionSnapshot::new(smbios, env_vars.iter().copied()));
 
         assert_eq!(expected, detected);
     }
+
+    #[test]
+    fn test_detector_evaluate() {
+        let smbios = Smbios::from(
+            SmbiosPattern::new()
+                .with_bios_vendor("test_bios_vendor")
+                .with_sys_vendor("other_sys_vendor"),
+        );
+
+        let candidate = Detector::new(
+            ComputeEnvironment::Testing,
+            smbios::TESTING,
+            &["TESTING_ENV", "TESTING_OTHER_ENV"],
+        )
+        .evaluate(&DetectionSnapshot::new(smbios, ["TESTING_ENV"]));
+
+        assert_eq!(ComputeEnvironment::Testing, candidate.environment);
+        assert_eq!(vec![SmbiosField::BiosVendor], candidate.smbios.matched);
+        assert_eq!(vec![SmbiosField::SysVendor], candidate.smbios.missing);
+        assert_eq!(vec![SmbiosField::ProductName], candidate.smbios.unavailable);
+        assert_eq!(MAX_INDIVIDUAL_WEIGHTING / 2, candidate.smbios.score);
+        assert_eq!(vec!["TESTING_ENV"], candidate.env_vars.matched);
+        assert_eq!(vec!["TESTING_OTHER_ENV"], candidate.env_vars.missing);
+        assert_eq!(
+            candidate.smbios.score + candidate.env_vars.score,
+            candidate.score
+        );
+    }
+
+    #[rstest]
+    #[case::no_signal(&[], &[], MAX_INDIVIDUAL_WEIGHTING / 2)]
+    #[case::signal(
+        &[],
+        &[Signal::ContainerRuntime(ContainerRuntime::Docker)],
+        MAX_INDIVIDUAL_WEIGHTING / 2 + MAX_INDIVIDUAL_WEIGHTING / 4
+    )]
+    #[case::env_over_signal(
+        &["TESTING_ENV"],
+        &[Signal::ContainerRuntime(ContainerRuntime::Docker)],
+        MAX_INDIVIDUAL_WEIGHTING / 2 + MAX_INDIVIDUAL_WEIGHTING
+    )]
+    fn test_detector_signals(
+        #[case] env_vars: &'static [&'static str],
+        #[case] signals: &'static [Signal],
+        #[case] expected: u16,
+    ) {
+        let snapshot = DetectionSnapshot::new(Smbios::default(), env_vars.iter().copied())
+            .with_signals(signals.iter().copied());
+
+        let candidate = Detector::new(
+            ComputeEnvironment::Testing,
+            SmbiosPattern::new(),
+            &["TESTING_ENV"],
+        )
+        .with_signals(signal::CONTAINER)
+        .evaluate(&snapshot);
+
+        assert_eq!(expected, candidate.score);
+        assert_eq!(signals, candidate.signals.matched);
+        assert_eq!(
+            signal::CONTAINER.len(),
+            candidate.signals.matched.len() + candidate.signals.missing.len()
+        );
+    }
+
+    #[rstest]
+    #[case::no_signal(smbios::EMPTY.into(), &[], MAX_INDIVIDUAL_WEIGHTING / 2)]
+    #[case::tcg(
+        smbios::EMPTY.into(),
+        &[Signal::Hypervisor(Hypervisor::Qemu)],
+        MAX_INDIVIDUAL_WEIGHTING + MAX_INDIVIDUAL_WEIGHTING / 2
+    )]
+    #[case::kvm(
+        smbios::EMPTY.into(),
+        &[Signal::Hypervisor(Hypervisor::Kvm)],
+        MAX_INDIVIDUAL_WEIGHTING / 4 + MAX_INDIVIDUAL_WEIGHTING / 2
+    )]
+    #[case::smbios_over_signal(
+        smbios::QEMU.into(),
+        &[Signal::Hypervisor(Hypervisor::Kvm)],
+        MAX_INDIVIDUAL_WEIGHTING + MAX_INDIVIDUAL_WEIGHTING / 2
+    )]
+    fn test_detector_platform_signals(
+        #[case] smbios: Smbios,
+        #[case] signals: &'static [Signal],
+        #[case] expected: u16,
+    ) {
+        let snapshot = DetectionSnapshot::new(smbios, []).with_signals(signals.iter().copied());
+
+        let candidate = ComputeEnvironment::Qemu.detector().evaluate(&snapshot);
+
+        assert_eq!(expected, candidate.score);
+        assert_eq!(signals, candidate.signals.matched);
+        assert_eq!(0, candidate.signals.runtime_score);
+    }
+
+    #[rstest]
+    #[case::none(&[], &[], MAX_TOTAL_WEIGHTING, &[])]
+    #[case::env_var(&["TESTING_LEGACY_ENV"], &[], 0, &["TESTING_LEGACY_ENV"])]
+    #[case::signal(
+        &[],
+        &[Signal::Hypervisor(Hypervisor::Qemu)],
+        0,
+        &["QEMU (hypervisor)"]
+    )]
+    fn test_detector_exclusions(
+        #[case] env_vars: &'static [&'static str],
+        #[case] signals: &'static [Signal],
+        #[case] expected_score: u16,
+        #[case] expected_exclusions: &[&str],
+    ) {
+        let snapshot = DetectionSnapshot::new(
+            Smbios::from(smbios::TESTING),
+            ["TESTING_ENV"].into_iter().chain(env_vars.iter().copied()),
+        )
+        .with_signals(signals.iter().copied());
+
+        let candidate = Detector::new(
+            ComputeEnvironment::Testing,
+            smbios::TESTING,
+            &["TESTING_ENV"],
+        )
+        .with_exclusions(&[
+            Exclusion::EnvVar("TESTING_LEGACY_ENV"),
+            Exclusion::Signal(Signal::Hypervisor(Hypervisor::Qemu)),
+        ])
+        .evaluate(&snapshot);
+
+        assert_eq!(expected_score, candidate.score);
+        assert_eq!(expected_exclusions, candidate.exclusions);
+    }
 }
@@ -145,11 +145,39 @@ pub fn hasenv(name: &str) -> bool {
     std::env::var_os(name).is_some()
 }
 
+/// Returns `true` if the environment variable is set and its value starts with `prefix`.
+///
+/// Like [`hasenv`], this uses libc to compare the value in place, without ever copying it.
+#[cfg(unix)]
+pub fn env_starts_with(name: &str, prefix: &str) -> bool {
+    let Ok(k) = std::ffi::CString::new(name) else {
+        return false;
+    };
+    let v = unsafe { libc::getenv(k.as_ptr()) } as *const libc::c_char;
+    if v.is_null() {
+        return false;
+    }
+    // Borrow the value where libc stores it.
+    let v = unsafe { std::ffi::CStr::from_ptr(v) };
+    v.to_bytes().starts_with(prefix.as_bytes())
+}
+
+/// Returns `true` if the environment variable is set and its value starts with `prefix`.
+///
+/// This always returns `false` on non-UNIX systems, where the value can't be read without copying
+/// it.
+#[cfg(not(unix))]
+pub fn env_starts_with(_name: &str, _prefix: &str) -> bool {
+    false
+}
+
 #[cfg(test)]
 mod tests {
     use std::env;
 
-    use super::hasenv;
+    use rstest::rstest;
+
+    use super::{env_starts_with, hasenv};
 
     #[test]
     fn test_hasenv() {
@@ -161,4 +189,19 @@ mod tests {
         assert!(hasenv(var));
         assert!(!hasenv(&format!("{var}_NOT_SET")));
     }
+
+    #[cfg(unix)]
+    #[rstest]
+    #[case::prefix("AWS_ECS", true)]
+    #[case::full("AWS_ECS_FARGATE", true)]
+    #[case::longer("AWS_ECS_FARGATE_SPOT", false)]
+    #[case::case_sensitive("aws_ecs", false)]
+    #[case::empty("", true)]
+    fn test_env_starts_with(#[case] prefix: &str, #[case] expected: bool) {
+        let var = "TEST_VAR_ENV_DETECTOR_VALUE";
+        env::set_var(var, "AWS_ECS_FARGATE");
+
+        assert_eq!(expected, env_starts_with(var, prefix));
+        assert!(!env_starts_with(&format!("{var}_NOT_SET"), ""));
+    }
 }
@@ -1,9 +1,13 @@
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
-use crate::{detector::Detector, env_vars, smbios};
+use crate::{
+    detector::Detector, env_vars, exclusion, signal, smbios, Hypervisor, ManagedPlatform,
+    Orchestrator,
+};
 
 /// Supported compute environments that can be detected by this crate
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
 pub enum ComputeEnvironment {
     // AWS supported platforms.
     /// Amazon Elastic Compute Cloud (EC2)
@@ -53,7 +57,11 @@ pub enum ComputeEnvironment {
     /// QEMU
     Qemu,
 
+    /// User-defined environment, detected by a custom [`Detector`].
+    Custom(CustomEnvironment),
+
     #[cfg(test)]
+    /// Testing environment
     Testing,
 }
 
@@ -64,36 +72,71 @@ impl ComputeEnvironment {
 
     pub(crate) fn detector(&self) -> Detector {
         match self {
-            Self::AwsEc2 => Detector::new(*self, smbios::AWS, env_vars::EMPTY),
-            Self::AwsEcs => Detector::new(*self, smbios::EMPTY, env_vars::AWS_ECS),
-            Self::AwsLambda => Detector::new(*self, smbios::EMPTY, env_vars::AWS_LAMBDA),
-            Self::AwsKubernetes => Detector::new(*self, smbios::AWS, env_vars::KUBERNETES),
-            Self::AwsNomad => Detector::new(*self, smbios::AWS, env_vars::NOMAD),
+            Self::AwsEc2 => {
+                Detector::new(*self, smbios::AWS, env_vars::EMPTY).with_signals(signal::AWS)
+            }
+            Self::AwsEcs => Detector::new(*self, smbios::EMPTY, env_vars::AWS_ECS)
+                .with_signals(signal::AWS_ECS)
+                .with_exclusions(exclusion::AWS_ECS),
+            Self::AwsLambda => Detector::new(*self, smbios::EMPTY, env_vars::AWS_LAMBDA)
+                .with_signals(signal::AWS_LAMBDA)
+                .with_exclusions(exclusion::AWS_LAMBDA),
+            Self::AwsKubernetes => Detector::new(*self, smbios::AWS, env_vars::KUBERNETES)
+                .with_signals(signal::AWS_KUBERNETES)
+                .with_exclusions(exclusion::KUBERNETES),
+            Self::AwsNomad => {
+                Detector::new(*self, smbios::AWS, env_vars::NOMAD).with_signals(signal::AWS_NOMAD)
+            }
             Self::AzureContainerApps => {
                 Detector::new(*self, smbios::AZURE, env_vars::AZURE_CONTAINER_APPS)
+                    .with_signals(signal::AZURE_CONTAINER)
             }
             Self::AzureContainerAppsJob => {
                 Detector::new(*self, smbios::AZURE, env_vars::AZURE_CONTAINER_APPS_JOB)
+                    .with_signals(signal::AZURE_CONTAINER)
             }
             Self::AzureContainerInstance => {
                 Detector::new(*self, smbios::EMPTY, env_vars::AZURE_CONTAINER_INSTANCE)
+                    .with_signals(signal::CONTAINER)
+            }
+            Self::AzureKubernetes => Detector::new(*self, smbios::AZURE, env_vars::KUBERNETES)
+                .with_signals(signal::AZURE_KUBERNETES)
+                .with_exclusions(exclusion::KUBERNETES),
+            Self::AzureVM => {
+                Detector::new(*self, smbios::AZURE, env_vars::EMPTY).with_signals(signal::AZURE)
             }
-            Self::AzureKubernetes => Detector::new(*self, smbios::AZURE, env_vars::KUBERNETES),
-            Self::AzureVM => Detector::new(*self, smbios::AZURE, env_vars::EMPTY),
-            Self::AzureNomad => Detector::new(*self, smbios::AZURE, env_vars::NOMAD),
+            Self::AzureNomad => Detector::new(*self, smbios::AZURE, env_vars::NOMAD)
+                .with_signals(signal::AZURE_NOMAD),
             Self::GcpCloudRunGen1 => {
                 Detector::new(*self, smbios::EMPTY, env_vars::GCP_CLOUD_RUN_SERVICE)
             }
             Self::GcpCloudRunGen2 => {
                 Detector::new(*self, smbios::GCP, env_vars::GCP_CLOUD_RUN_SERVICE)
+                    .with_signals(signal::GCP)
+            }
+            Self::GcpCloudRunJob => Detector::new(*self, smbios::GCP, env_vars::GCP_CLOUD_RUN_JOB)
+                .with_signals(signal::GCP),
+            Self::GcpComputeEngine => {
+                Detector::new(*self, smbios::GCP, env_vars::EMPTY).with_signals(signal::GCP)
+            }
+            Self::GcpKubernetes => Detector::new(*self, smbios::GCP, env_vars::KUBERNETES)
+                .with_signals(signal::GCP_KUBERNETES)
+                .with_exclusions(exclusion::KUBERNETES),
+            Self::GcpNomad => {
+                Detector::new(*self, smbios::GCP, env_vars::NOMAD).with_signals(signal::GCP_NOMAD)
+            }
+            Self::Kubernetes => Detector::new(*self, smbios::EMPTY, env_vars::KUBERNETES)
+                .with_signals(signal::KUBERNETES)
+                .with_exclusions(exclusion::KUBERNETES),
+            Self::Nomad => {
+                Detector::new(*self, smbios::EMPTY, env_vars::NOMAD).with_signals(signal::NOMAD)
+            }
+            Self::Qemu => {
+                Detector::new(*self, smbios::QEMU, env_vars::EMPTY).with_signals(signal::QEMU)
             }
-            Self::GcpCloudRunJob => Detector::new(*self, smbios::GCP, env_vars::GCP_CLOUD_RUN_JOB),
-            Self::GcpComputeEngine => Detector::new(*self, smbios::GCP, env_vars::EMPTY),
-            Self::GcpKubernetes => Detector::new(*self, smbios::GCP, env_vars::KUBERNETES),
-            Self::GcpNomad => Detector::new(*self, smbios::GCP, env_vars::NOMAD),
-            Self::Kubernetes => Detector::new(*self, smbios::EMPTY, env_vars::KUBERNETES),
-            Self::Nomad => Detector::new(*self, smbios::EMPTY, env_vars::NOMAD),
-            Self::Qemu => Detector::new(*self, smbios::QEMU, env_vars::EMPTY),
+            // Custom environments are never part of the built-in detectors, and always come with
+            // their own detector instead.
+            Self::Custom(_) => Detector::new(*self, smbios::EMPTY, env_vars::EMPTY),
 
             #[cfg(test)]
             Self::Testing => Detector::new(*self, smbios::EMPTY, env_vars::EMPTY),
@@ -123,12 +166,67 @@ impl ComputeEnvironment {
             Self::Kubernetes => "Kubernetes",
             Self::Nomad => "Nomad",
             Self::Qemu => "QEMU",
+            Self::Custom(custom) => custom.name,
 
             #[cfg(test)]
             Self::Testing => "Testing",
         }
     }
 
+    /// Unique identifier of the [`ComputeEnvironment`].
+    ///
+    /// This is the same as [`ComputeEnvironment::platform_code`] when that code is unique, and
+    /// uses a more specific identifier otherwise. Custom environments use their platform code.
+    ///
+    /// Built-in environments can be parsed back from this identifier with [`FromStr`].
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::AwsEc2 => "aws_ec2",
+            Self::AwsEcs => "aws_ecs",
+            Self::AwsLambda => "aws_lambda",
+            Self::AwsKubernetes => "aws_eks",
+            Self::AwsNomad => "aws_nomad",
+            Self::AzureContainerApps => "azure_container_apps",
+            Self::AzureContainerAppsJob => "azure_container_apps_job",
+            Self::AzureContainerInstance => "azure_container_instances",
+            Self::AzureKubernetes => "azure_aks",
+            Self::AzureVM => "azure_vm",
+            Self::AzureNomad => "azure_nomad",
+            Self::GcpCloudRunGen1 => "gcp_cloud_run_gen1",
+            Self::GcpCloudRunGen2 => "gcp_cloud_run_gen2",
+            Self::GcpCloudRunJob => "gcp_cloud_run_job",
+            Self::GcpComputeEngine => "gcp_compute_engine",
+            Self::GcpKubernetes => "gcp_kubernetes_engine",
+            Self::GcpNomad => "gcp_nomad",
+            Self::Kubernetes => "kubernetes",
+            Self::Nomad => "nomad",
+            Self::Qemu => "qemu",
+            Self::Custom(custom) => custom.code,
+
+            #[cfg(test)]
+            Self::Testing => "testing",
+        }
+    }
+
+    /// Built-in [`ComputeEnvironment`] matching a unique identifier.
+    #[cfg_attr(not(feature = "serde"), allow(unused))]
+    pub(crate) fn from_id(id: &str) -> Option<Self> {
+        Self::iter().find(|ce| ce.id() == id)
+    }
+
+    /// Built-in [`ComputeEnvironment`]s matching a platform code.
+    ///
+    /// As platform codes are not unique, this may return multiple environments. For example,
+    /// `nomad` matches [`ComputeEnvironment::Nomad`] as well as Nomad on every cloud provider.
+    /// These can be told apart with [`ComputeEnvironment::cloud_provider`].
+    ///
+    /// See [`ComputeEnvironment::platform_code`].
+    pub fn from_platform_code(code: &str) -> Vec<Self> {
+        Self::iter()
+            .filter(|ce| ce.platform_code() == code)
+            .collect()
+    }
+
     /// Compute Platform code
     ///
     /// This corresponds to the `cloud.platform` attribute in OpenTelemetry semantic conventions
@@ -169,6 +267,7 @@ impl ComputeEnvironment {
             Self::Kubernetes => "kubernetes",
             Self::Nomad => "nomad",
             Self::Qemu => "qemu",
+            Self::Custom(custom) => custom.code,
 
             #[cfg(test)]
             Self::Testing => "testing",
@@ -196,11 +295,62 @@ impl ComputeEnvironment {
             | Self::GcpKubernetes
             | Self::GcpNomad => Some(CloudProvider::GoogleCloud),
             Self::Kubernetes | Self::Nomad | Self::Qemu => None,
+            Self::Custom(custom) => custom.cloud_provider,
+
+            #[cfg(test)]
+            Self::Testing => None,
+        }
+    }
+
+    /// [`ManagedPlatform`] for this compute environment
+    pub fn platform(&self) -> Option<ManagedPlatform> {
+        match self {
+            Self::AwsEc2 | Self::AwsNomad => Some(ManagedPlatform::AwsEc2),
+            Self::AwsEcs => Some(ManagedPlatform::AwsEcs),
+            Self::AwsLambda => Some(ManagedPlatform::AwsLambda),
+            // We're assuming Kubernetes on AWS = EKS
+            Self::AwsKubernetes => Some(ManagedPlatform::AwsEks),
+            Self::AzureContainerApps => Some(ManagedPlatform::AzureContainerApps),
+            Self::AzureContainerAppsJob => Some(ManagedPlatform::AzureContainerAppsJob),
+            Self::AzureContainerInstance => Some(ManagedPlatform::AzureContainerInstance),
+            // We're assuming Kubernetes on Azure = AKS
+            Self::AzureKubernetes => Some(ManagedPlatform::AzureAks),
+            Self::AzureVM | Self::AzureNomad => Some(ManagedPlatform::AzureVM),
+            Self::GcpCloudRunGen1 => Some(ManagedPlatform::GcpCloudRunGen1),
+            Self::GcpCloudRunGen2 => Some(ManagedPlatform::GcpCloudRunGen2),
+            Self::GcpCloudRunJob => Some(ManagedPlatform::GcpCloudRunJob),
+            Self::GcpComputeEngine | Self::GcpNomad => Some(ManagedPlatform::GcpComputeEngine),
+            // We're assuming Kubernetes on GCP = GKE
+            Self::GcpKubernetes => Some(ManagedPlatform::GcpKubernetesEngine),
+            Self::Kubernetes | Self::Nomad | Self::Qemu => None,
+            Self::Custom(custom) => Some(ManagedPlatform::Custom(*custom)),
 
             #[cfg(test)]
             Self::Testing => None,
         }
     }
+
+    /// [`Orchestrator`] for this compute environment
+    pub fn orchestrator(&self) -> Option<Orchestrator> {
+        match self {
+            Self::AwsKubernetes
+            | Self::AzureKubernetes
+            | Self::GcpKubernetes
+            | Self::Kubernetes => Some(Orchestrator::Kubernetes),
+            Self::AwsNomad | Self::AzureNomad | Self::GcpNomad | Self::Nomad => {
+                Some(Orchestrator::Nomad)
+            }
+            _ => None,
+        }
+    }
+
+    /// [`Hypervisor`] for this compute environment
+    pub fn hypervisor(&self) -> Option<Hypervisor> {
+        match self {
+            Self::Qemu => Some(Hypervisor::Qemu),
+            _ => None,
+        }
+    }
 }
 
 impl Display for ComputeEnvironment {
@@ -209,6 +359,71 @@ impl Display for ComputeEnvironment {
     }
 }
 
+/// Parses a built-in [`ComputeEnvironment`] from either its unique identifier (see
+/// [`ComputeEnvironment::id`]) or its display name (see [`ComputeEnvironment::as_str`]), ignoring
+/// case.
+impl FromStr for ComputeEnvironment {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::iter()
+            .find(|ce| ce.id().eq_ignore_ascii_case(s))
+            .or_else(|| Self::iter().find(|ce| ce.as_str().eq_ignore_ascii_case(s)))
+            .ok_or_else(|| ParseError::new(ParseErrorKind::ComputeEnvironment, s))
+    }
+}
+
+impl TryFrom<&str> for ComputeEnvironment {
+    type Error = ParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// User-defined compute environment.
+///
+/// # Examples
+///
+/// ```
+/// use apollo_environment_detector::{CloudProvider, ComputeEnvironment, CustomEnvironment};
+///
+/// const ACME: ComputeEnvironment = ComputeEnvironment::Custom(
+///     CustomEnvironment::new("Acme Platform", "acme_platform").with_cloud_provider(CloudProvider::Aws),
+/// );
+///
+/// assert_eq!("Acme Platform", ACME.as_str());
+/// assert_eq!("acme_platform", ACME.platform_code());
+/// assert_eq!(Some(CloudProvider::Aws), ACME.cloud_provider());
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct CustomEnvironment {
+    name: &'static str,
+    code: &'static str,
+    cloud_provider: Option<CloudProvider>,
+}
+
+impl CustomEnvironment {
+    /// Create a new custom environment with a human-readable name and a platform code.
+    ///
+    /// See [`ComputeEnvironment::as_str`] and [`ComputeEnvironment::platform_code`].
+    pub const fn new(name: &'static str, code: &'static str) -> Self {
+        Self {
+            name,
+            code,
+            cloud_provider: None,
+        }
+    }
+
+    /// Set the [`CloudProvider`] this environment runs on.
+    pub const fn with_cloud_provider(self, cloud_provider: CloudProvider) -> Self {
+        Self {
+            cloud_provider: Some(cloud_provider),
+            ..self
+        }
+    }
+}
+
 pub(crate) struct ComputeEnvironmentIter {
     idx: usize,
 }
@@ -252,13 +467,17 @@ impl Iterator for ComputeEnvironmentIter {
 }
 
 /// Supported cloud providers that can be detected by this crate.
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CloudProvider {
     /// Amazon Web Services
+    #[cfg_attr(feature = "serde", serde(rename = "aws"))]
     Aws,
     /// Microsoft Azure
+    #[cfg_attr(feature = "serde", serde(rename = "azure"))]
     Azure,
     /// Google Cloud Platform
+    #[cfg_attr(feature = "serde", serde(rename = "gcp"))]
     GoogleCloud,
 }
 
@@ -291,10 +510,70 @@ impl Display for CloudProvider {
     }
 }
 
+/// Parses a [`CloudProvider`] from either its code (see [`CloudProvider::code`]) or its display
+/// name (see [`CloudProvider::as_str`]), ignoring case.
+impl FromStr for CloudProvider {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Self::Aws, Self::Azure, Self::GoogleCloud]
+            .into_iter()
+            .find(|cp| cp.code().eq_ignore_ascii_case(s) || cp.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ParseError::new(ParseErrorKind::CloudProvider, s))
+    }
+}
+
+impl TryFrom<&str> for CloudProvider {
+    type Error = ParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// Error returned when parsing a [`ComputeEnvironment`] or a [`CloudProvider`] from a string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    value: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ParseErrorKind {
+    ComputeEnvironment,
+    CloudProvider,
+}
+
+impl ParseError {
+    fn new(kind: ParseErrorKind, value: &str) -> Self {
+        Self {
+            kind,
+            value: value.to_string(),
+        }
+    }
+
+    /// Value that could not be parsed.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            ParseErrorKind::ComputeEnvironment => "compute environment",
+            ParseErrorKind::CloudProvider => "cloud provider",
+        };
+        write!(f, "unknown {kind}: `{}`", self.value)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 #[cfg(test)]
 mod tests {
     use std::cmp::Ordering;
-    use std::collections::HashMap;
+    use std::collections::{HashMap, HashSet};
 
     use rstest::{fixture, rstest};
 
@@ -326,6 +605,78 @@ mod tests {
             .collect()
     }
 
+    #[rstest]
+    fn test_from_str() {
+        for environment in ComputeEnvironment::iter() {
+            assert_eq!(Ok(environment), environment.id().parse());
+            assert_eq!(Ok(environment), environment.as_str().parse());
+            assert_eq!(
+                Ok(environment),
+                ComputeEnvironment::try_from(environment.id().to_uppercase().as_str())
+            );
+        }
+    }
+
+    #[rstest]
+    fn test_unique_id() {
+        let ids: HashSet<_> = ComputeEnvironment::iter().map(|ce| ce.id()).collect();
+        assert_eq!(ComputeEnvironment::iter().count(), ids.len());
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("aws")]
+    #[case("gcp_cloud_run")]
+    fn test_from_str_invalid(#[case] value: &str) {
+        let err = value.parse::<ComputeEnvironment>().unwrap_err();
+        assert_eq!(value, err.value());
+        assert_eq!(
+            format!("unknown compute environment: `{value}`"),
+            err.to_string()
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "nomad",
+        &[
+            ComputeEnvironment::AwsNomad,
+            ComputeEnvironment::AzureNomad,
+            ComputeEnvironment::GcpNomad,
+            ComputeEnvironment::Nomad,
+        ]
+    )]
+    #[case(
+        "gcp_cloud_run",
+        &[
+            ComputeEnvironment::GcpCloudRunGen1,
+            ComputeEnvironment::GcpCloudRunGen2,
+            ComputeEnvironment::GcpCloudRunJob,
+        ]
+    )]
+    #[case("aws_eks", &[ComputeEnvironment::AwsKubernetes])]
+    #[case("unknown", &[])]
+    fn test_from_platform_code(#[case] code: &str, #[case] expected: &[ComputeEnvironment]) {
+        assert_eq!(expected, ComputeEnvironment::from_platform_code(code));
+    }
+
+    #[rstest]
+    #[case("aws", Ok(CloudProvider::Aws))]
+    #[case("AWS", Ok(CloudProvider::Aws))]
+    #[case("azure", Ok(CloudProvider::Azure))]
+    #[case("gcp", Ok(CloudProvider::GoogleCloud))]
+    #[case("Google Cloud", Ok(CloudProvider::GoogleCloud))]
+    #[case(
+        "google",
+        Err(ParseError::new(ParseErrorKind::CloudProvider, "google"))
+    )]
+    fn test_cloud_provider_from_str(
+        #[case] value: &str,
+        #[case] expected: Result<CloudProvider, ParseError>,
+    ) {
+        assert_eq!(expected, CloudProvider::try_from(value));
+    }
+
     #[rstest]
     fn test_specificity(
         #[values(
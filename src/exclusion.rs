@@ -0,0 +1,118 @@
+use std::fmt::Display;
+
+use crate::{DetectionSnapshot, EnvValue, Signal, SmbiosField};
+
+pub const AWS_ECS: &[Exclusion] = &[Exclusion::Signal(Signal::EnvValue(EnvValue::AwsLambda))];
+pub const AWS_LAMBDA: &[Exclusion] = &[
+    Exclusion::Signal(Signal::EnvValue(EnvValue::AwsEcsFargate)),
+    Exclusion::Signal(Signal::EnvValue(EnvValue::AwsEcsEc2)),
+];
+pub const EMPTY: &[Exclusion] = &[];
+// Azure Container Apps run on Kubernetes, and expose the same environment variables.
+pub const KUBERNETES: &[Exclusion] = &[
+    Exclusion::EnvVar("CONTAINER_APP_ENV_DNS_SUFFIX"),
+    Exclusion::EnvVar("CONTAINER_APP_HOSTNAME"),
+    Exclusion::EnvVar("CONTAINER_APP_JOB_EXECUTION_NAME"),
+    Exclusion::EnvVar("CONTAINER_APP_JOB_NAME"),
+    Exclusion::EnvVar("CONTAINER_APP_NAME"),
+    Exclusion::EnvVar("CONTAINER_APP_PORT"),
+    Exclusion::EnvVar("CONTAINER_APP_REPLICA_NAME"),
+    Exclusion::EnvVar("CONTAINER_APP_REVISION"),
+];
+
+/// Evidence ruling out a [`Detector`](crate::Detector), regardless of its score.
+///
+/// # Examples
+///
+/// ```
+/// use apollo_environment_detector::{
+///     ComputeEnvironment, CustomEnvironment, Detector, Exclusion, SmbiosField, SmbiosPattern,
+/// };
+///
+/// const ACME: Detector = Detector::new(
+///     ComputeEnvironment::Custom(CustomEnvironment::new("Acme Platform", "acme_platform")),
+///     SmbiosPattern::new().with_sys_vendor("acme"),
+///     &["ACME_NODE_ID", "ACME_REGION"],
+/// )
+/// .with_exclusions(&[
+///     Exclusion::EnvVar("ACME_LEGACY_NODE"),
+///     Exclusion::Smbios(SmbiosField::ProductName, "legacy"),
+/// ]);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Exclusion {
+    /// The environment variable is set.
+    EnvVar(&'static str),
+    /// The SMBIOS field matches the pattern, as in [`SmbiosPattern`](crate::SmbiosPattern).
+    Smbios(SmbiosField, &'static str),
+    /// The signal was observed.
+    Signal(Signal),
+}
+
+impl Exclusion {
+    /// Returns `true` if this exclusion was observed in the snapshot.
+    pub(crate) fn matches(&self, snapshot: &DetectionSnapshot) -> bool {
+        match self {
+            Self::EnvVar(name) => snapshot.env_vars.contains(*name),
+            Self::Smbios(field, pattern) => snapshot
+                .smbios
+                .get(*field)
+                .is_some_and(|value| field.matches(value, pattern)),
+            Self::Signal(signal) => snapshot.signals.contains(signal),
+        }
+    }
+
+    /// Name of the environment variable, if any.
+    pub(crate) fn env_var(&self) -> Option<&'static str> {
+        match self {
+            Self::EnvVar(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Exclusion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EnvVar(name) => f.write_str(name),
+            Self::Smbios(field, pattern) => write!(f, "{field} matches \"{pattern}\""),
+            Self::Signal(signal) => write!(f, "{signal}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::Exclusion;
+    use crate::{
+        smbios::{Smbios, SmbiosPattern},
+        ContainerRuntime, DetectionSnapshot, Signal, SmbiosField,
+    };
+
+    #[rstest]
+    #[case::env_var(Exclusion::EnvVar("CONTAINER_APP_NAME"), true)]
+    #[case::env_var_missing(Exclusion::EnvVar("CONTAINER_APP_JOB_NAME"), false)]
+    #[case::smbios(Exclusion::Smbios(SmbiosField::SysVendor, "qemu"), true)]
+    #[case::smbios_mismatch(Exclusion::Smbios(SmbiosField::SysVendor, "vmware"), false)]
+    #[case::smbios_unavailable(Exclusion::Smbios(SmbiosField::ProductName, "qemu"), false)]
+    #[case::signal(
+        Exclusion::Signal(Signal::ContainerRuntime(ContainerRuntime::Docker)),
+        true
+    )]
+    #[case::signal_missing(
+        Exclusion::Signal(Signal::ContainerRuntime(ContainerRuntime::Podman)),
+        false
+    )]
+    fn test_exclusion_matches(#[case] exclusion: Exclusion, #[case] expected: bool) {
+        let snapshot = DetectionSnapshot::new(
+            Smbios::from(SmbiosPattern::new().with_sys_vendor("QEMU")),
+            ["CONTAINER_APP_NAME"],
+        )
+        .with_signals([Signal::ContainerRuntime(ContainerRuntime::Docker)]);
+
+        assert_eq!(expected, exclusion.matches(&snapshot));
+    }
+}
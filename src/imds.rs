@@ -0,0 +1,620 @@
+//! Cloud provider detection from the instance metadata services.
+//!
+//! Unlike the other detection sources, this makes network requests to the link-local metadata
+//! services, so it only runs when enabled with
+//! [`EnvironmentDetector::with_imds`](crate::EnvironmentDetector::with_imds). Only the status and
+//! a few marker headers or values of the responses are checked, none of the instance metadata is
+//! kept.
+
+use std::{
+    io::{Read, Write},
+    net::{IpAddr, SocketAddr, TcpStream},
+    time::{Duration, Instant},
+};
+
+use crate::CloudProvider;
+
+// All providers serve their metadata service on the same link-local address. The GCP metadata
+// server is also reachable as `metadata.google.internal`, but base URLs only accept IP addresses,
+// as DNS lookups cannot be bounded by a timeout.
+const DEFAULT_BASE_URL: &str = "http://169.254.169.254";
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(250);
+const DEFAULT_BUDGET: Duration = Duration::from_secs(1);
+
+// Responses are never expected to be larger than this, anything beyond is ignored.
+const MAX_RESPONSE_SIZE: u64 = 64 * 1024;
+
+const AWS_TOKEN_PATH: &str = "/latest/api/token";
+const AWS_METADATA_PATH: &str = "/latest/meta-data/";
+const AZURE_ENVIRONMENT_PATH: &str =
+    "/metadata/instance/compute/azEnvironment?api-version=2021-02-01&format=text";
+const GCP_METADATA_PATH: &str = "/computeMetadata/v1/";
+
+/// Configuration of the instance metadata service requests.
+///
+/// The metadata services of AWS (IMDSv2), Google Cloud and Azure are queried in turn, until one of
+/// them answers. Each request is bounded by [`ImdsConfig::with_timeout`], and all of them by
+/// [`ImdsConfig::with_budget`], so that detection outside of these cloud providers is only
+/// delayed by the budget at most.
+///
+/// Containers may not be able to reach the metadata service, such as on EC2 instances requiring
+/// IMDSv2 with a hop limit of 1.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use apollo_environment_detector::{EnvironmentDetector, ImdsConfig, MAX_INDIVIDUAL_WEIGHTING};
+///
+/// let detector = EnvironmentDetector::new().with_imds(
+///     ImdsConfig::new()
+///         .with_timeout(Duration::from_millis(100))
+///         .with_budget(Duration::from_millis(300)),
+/// );
+///
+/// let compute_env = detector.detect_one(MAX_INDIVIDUAL_WEIGHTING);
+/// println!("{:?}", compute_env);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImdsConfig {
+    aws_base_url: String,
+    azure_base_url: String,
+    gcp_base_url: String,
+    timeout: Duration,
+    budget: Duration,
+}
+
+impl ImdsConfig {
+    /// Create a new [`ImdsConfig`] querying the default metadata service addresses, with a timeout
+    /// of 250ms per request and a budget of 1s.
+    pub fn new() -> Self {
+        Self {
+            aws_base_url: DEFAULT_BASE_URL.to_string(),
+            azure_base_url: DEFAULT_BASE_URL.to_string(),
+            gcp_base_url: DEFAULT_BASE_URL.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            budget: DEFAULT_BUDGET,
+        }
+    }
+
+    /// Query the AWS metadata service at another base URL, such as `http://[fd00:ec2::254]`.
+    ///
+    /// Only `http://` URLs with an IP address are supported.
+    pub fn with_aws_base_url(self, base_url: impl Into<String>) -> Self {
+        Self {
+            aws_base_url: base_url.into(),
+            ..self
+        }
+    }
+
+    /// Query the Azure metadata service at another base URL.
+    ///
+    /// Only `http://` URLs with an IP address are supported.
+    pub fn with_azure_base_url(self, base_url: impl Into<String>) -> Self {
+        Self {
+            azure_base_url: base_url.into(),
+            ..self
+        }
+    }
+
+    /// Query the Google Cloud metadata server at another base URL.
+    ///
+    /// Only `http://` URLs with an IP address are supported.
+    pub fn with_gcp_base_url(self, base_url: impl Into<String>) -> Self {
+        Self {
+            gcp_base_url: base_url.into(),
+            ..self
+        }
+    }
+
+    /// Maximum duration of each request, from connecting to reading the response.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    /// Maximum duration of all requests.
+    pub fn with_budget(self, budget: Duration) -> Self {
+        Self { budget, ..self }
+    }
+}
+
+impl Default for ImdsConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Detect the cloud provider from its instance metadata service.
+pub(crate) fn detect(config: &ImdsConfig) -> Option<CloudProvider> {
+    let client = Client {
+        timeout: config.timeout,
+        deadline: Instant::now() + config.budget,
+    };
+
+    detect_aws(&client, &config.aws_base_url)
+        .or_else(|| detect_gcp(&client, &config.gcp_base_url))
+        .or_else(|| detect_azure(&client, &config.azure_base_url))
+}
+
+/// IMDSv2 requires a session token, obtained with a `PUT` request that IMDSv1-only services and
+/// other clouds emulating the EC2 metadata service don't support.
+fn detect_aws(client: &Client, base_url: &str) -> Option<CloudProvider> {
+    let token = client
+        .request(
+            "PUT",
+            base_url,
+            AWS_TOKEN_PATH,
+            &[("X-aws-ec2-metadata-token-ttl-seconds", "60")],
+        )
+        .filter(|response| response.status == 200)?
+        .body;
+    let token = std::str::from_utf8(&token).ok()?.trim();
+    if token.is_empty() {
+        return None;
+    }
+
+    client
+        .request(
+            "GET",
+            base_url,
+            AWS_METADATA_PATH,
+            &[("X-aws-ec2-metadata-token", token)],
+        )
+        .filter(|response| response.status == 200)
+        .map(|_| CloudProvider::Aws)
+}
+
+/// The metadata server echoes the `Metadata-Flavor` header.
+fn detect_gcp(client: &Client, base_url: &str) -> Option<CloudProvider> {
+    client
+        .request(
+            "GET",
+            base_url,
+            GCP_METADATA_PATH,
+            &[("Metadata-Flavor", "Google")],
+        )
+        .filter(|response| {
+            response.status == 200 && response.header("metadata-flavor") == Some("Google")
+        })
+        .map(|_| CloudProvider::GoogleCloud)
+}
+
+/// The Azure environment is one of `AzurePublicCloud`, `AzureUSGovernmentCloud`, etc.
+fn detect_azure(client: &Client, base_url: &str) -> Option<CloudProvider> {
+    client
+        .request(
+            "GET",
+            base_url,
+            AZURE_ENVIRONMENT_PATH,
+            &[("Metadata", "true")],
+        )
+        .filter(|response| response.status == 200 && response.body.starts_with(b"Azure"))
+        .map(|_| CloudProvider::Azure)
+}
+
+/// Minimal HTTP/1.1 client, sending a single request per connection.
+struct Client {
+    timeout: Duration,
+    deadline: Instant,
+}
+
+impl Client {
+    fn request(
+        &self,
+        method: &str,
+        base_url: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+    ) -> Option<Response> {
+        let deadline = self.deadline.min(Instant::now() + self.timeout);
+        let remaining =
+            || Some(deadline.saturating_duration_since(Instant::now())).filter(|d| !d.is_zero());
+
+        let endpoint = Endpoint::parse(base_url)?;
+        let mut stream = TcpStream::connect_timeout(&endpoint.address, remaining()?).ok()?;
+
+        let mut request = format!(
+            "{method} {}{path} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: 0\r\n",
+            endpoint.path, endpoint.authority
+        );
+        for (name, value) in headers {
+            request.push_str(&format!("{name}: {value}\r\n"));
+        }
+        request.push_str("\r\n");
+        stream.set_write_timeout(Some(remaining()?)).ok()?;
+        stream.write_all(request.as_bytes()).ok()?;
+
+        // The read timeout applies to each read, so it is shortened as the deadline approaches, and
+        // reading stops once it has passed.
+        let mut stream = stream.take(MAX_RESPONSE_SIZE);
+        let mut response = Vec::new();
+        let mut buffer = [0; 4096];
+        loop {
+            stream.get_ref().set_read_timeout(Some(remaining()?)).ok()?;
+            match stream.read(&mut buffer).ok()? {
+                0 => break,
+                n => response.extend_from_slice(&buffer[..n]),
+            }
+        }
+
+        Response::parse(&response)
+    }
+}
+
+/// Address of an `http://` base URL.
+///
+/// Host names are not supported, so that connecting never requires a DNS lookup.
+#[derive(Debug, PartialEq, Eq)]
+struct Endpoint<'a> {
+    authority: &'a str,
+    address: SocketAddr,
+    path: &'a str,
+}
+
+impl<'a> Endpoint<'a> {
+    fn parse(url: &'a str) -> Option<Self> {
+        let url = url.strip_prefix("http://")?;
+        let (authority, path) = url.split_at(url.find('/').unwrap_or(url.len()));
+        let (host, port) = match authority.rsplit_once(':') {
+            // The last colon of an IPv6 address without a port is within the brackets.
+            Some((host, port)) if !port.ends_with(']') => (host, port.parse().ok()?),
+            _ => (authority, 80),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+
+        Some(Self {
+            authority,
+            address: SocketAddr::new(host.parse::<IpAddr>().ok()?, port),
+            path: path.trim_end_matches('/'),
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Response {
+    status: u16,
+    /// Header names are lowercase.
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn parse(response: &[u8]) -> Option<Self> {
+        let head_len = response
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")?;
+        let head = std::str::from_utf8(&response[..head_len]).ok()?;
+        let body = &response[head_len + 4..];
+
+        let mut lines = head.split("\r\n");
+        let status = lines.next()?.split(' ').nth(1)?.parse().ok()?;
+        let headers = lines
+            .filter_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                Some((name.trim().to_lowercase(), value.trim().to_string()))
+            })
+            .collect();
+
+        let mut response = Self {
+            status,
+            headers,
+            body: Vec::new(),
+        };
+        response.body = match response.header("transfer-encoding") {
+            Some(encoding) if encoding.eq_ignore_ascii_case("chunked") => decode_chunked(body)?,
+            _ => body.to_vec(),
+        };
+        Some(response)
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find_map(|(header, value)| (header == name).then_some(value.as_str()))
+    }
+}
+
+/// Decode a body sent with the chunked transfer encoding.
+fn decode_chunked(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    loop {
+        let line_len = body.windows(2).position(|window| window == b"\r\n")?;
+        let size = std::str::from_utf8(&body[..line_len]).ok()?;
+        let size = size.split(';').next()?.trim();
+        let size = usize::from_str_radix(size, 16).ok()?;
+        if size == 0 {
+            return Some(decoded);
+        }
+
+        let chunk = body.get(line_len + 2..line_len + 2 + size)?;
+        decoded.extend_from_slice(chunk);
+        body = body.get(line_len + 2 + size + 2..)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{ComputeEnvironment, EnvironmentDetector, FakeProbe, MAX_INDIVIDUAL_WEIGHTING};
+
+    /// Serve every connection with the response returned by the handler for the request head, and
+    /// return the base URL of the server.
+    fn serve(handler: fn(&str) -> Option<&'static str>) -> String {
+        listen(move |mut stream| {
+            let request = read_request(&mut stream);
+            match handler(&request) {
+                Some(response) => {
+                    let _ = stream.write_all(response.as_bytes());
+                }
+                // Keep the connection open without ever responding.
+                None => thread::sleep(Duration::from_secs(30)),
+            }
+        })
+    }
+
+    /// Handle every connection in turn, and return the base URL of the server.
+    fn listen(handle: impl Fn(TcpStream) + Send + 'static) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+        thread::spawn(move || {
+            for stream in listener.incoming().map_while(Result::ok) {
+                handle(stream);
+            }
+        });
+
+        base_url
+    }
+
+    fn read_request(stream: &mut TcpStream) -> String {
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+        while !request.ends_with(b"\r\n\r\n") {
+            match stream.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => request.extend_from_slice(&buffer[..n]),
+            }
+        }
+        String::from_utf8(request).unwrap()
+    }
+
+    /// Mock serving the metadata service of every provider, as long as the expected headers are
+    /// sent.
+    fn mock(request: &str) -> Option<&'static str> {
+        let response = if request.starts_with("PUT /latest/api/token ")
+            && request.contains("\r\nX-aws-ec2-metadata-token-ttl-seconds: ")
+        {
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\ntoken"
+        } else if request.starts_with("GET /latest/meta-data/ ")
+            && request.contains("\r\nX-aws-ec2-metadata-token: token\r\n")
+        {
+            "HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\ninstance-id"
+        } else if request.starts_with("GET /computeMetadata/v1/ ")
+            && request.contains("\r\nMetadata-Flavor: Google\r\n")
+        {
+            "HTTP/1.1 200 OK\r\nMetadata-Flavor: Google\r\nContent-Length: 9\r\n\r\ninstance/"
+        } else if request.starts_with("GET /metadata/instance/compute/azEnvironment?")
+            && request.contains("\r\nMetadata: true\r\n")
+        {
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n10\r\nAzurePublicCloud\r\n0\r\n\r\n"
+        } else {
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n"
+        };
+        Some(response)
+    }
+
+    fn not_found(_: &str) -> Option<&'static str> {
+        Some("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n")
+    }
+
+    // IMDSv1 services and other clouds emulating the EC2 metadata service don't issue tokens, and
+    // serve the metadata without requiring any header.
+    fn imdsv1(request: &str) -> Option<&'static str> {
+        if request.starts_with("GET ") {
+            Some("HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\ninstance-id")
+        } else {
+            Some("HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\n\r\n")
+        }
+    }
+
+    fn unresponsive(_: &str) -> Option<&'static str> {
+        None
+    }
+
+    /// Base URL where nothing listens.
+    fn closed() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    fn config(aws: &str, azure: &str, gcp: &str) -> ImdsConfig {
+        ImdsConfig::new()
+            .with_aws_base_url(aws)
+            .with_azure_base_url(azure)
+            .with_gcp_base_url(gcp)
+            .with_timeout(Duration::from_secs(5))
+            .with_budget(Duration::from_secs(15))
+    }
+
+    #[rstest]
+    fn test_detect_aws() {
+        let mock = serve(mock);
+        let closed = closed();
+
+        assert_eq!(
+            Some(CloudProvider::Aws),
+            detect(&config(&mock, &closed, &closed))
+        );
+    }
+
+    #[rstest]
+    fn test_detect_gcp() {
+        let mock = serve(mock);
+        let not_found = serve(not_found);
+
+        assert_eq!(
+            Some(CloudProvider::GoogleCloud),
+            detect(&config(&not_found, &not_found, &mock))
+        );
+    }
+
+    #[rstest]
+    fn test_detect_azure() {
+        let mock = serve(mock);
+        let not_found = serve(not_found);
+
+        assert_eq!(
+            Some(CloudProvider::Azure),
+            detect(&config(&not_found, &mock, &not_found))
+        );
+    }
+
+    #[rstest]
+    fn test_detect_none() {
+        let not_found = serve(not_found);
+        let imdsv1 = serve(imdsv1);
+        let closed = closed();
+
+        assert_eq!(None, detect(&config(&imdsv1, &not_found, &closed)));
+        assert_eq!(
+            None,
+            detect(&config(
+                "not a url",
+                "https://127.0.0.1",
+                "http://localhost"
+            ))
+        );
+    }
+
+    #[rstest]
+    fn test_detect_budget() {
+        let unresponsive = serve(unresponsive);
+        let config = config(&unresponsive, &unresponsive, &unresponsive)
+            .with_timeout(Duration::from_millis(500))
+            .with_budget(Duration::from_secs(1));
+
+        // Without the budget, the three requests would take at least 1.5s. The server keeps the
+        // connections open for much longer than the assertions allow.
+        let start = Instant::now();
+        assert_eq!(None, detect(&config));
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_secs(1), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(1500), "{elapsed:?}");
+    }
+
+    #[rstest]
+    fn test_request_unresponsive() {
+        let unresponsive = serve(unresponsive);
+        let client = Client {
+            timeout: Duration::from_secs(5),
+            deadline: Instant::now() + Duration::from_millis(300),
+        };
+
+        let start = Instant::now();
+        assert_eq!(None, client.request("GET", &unresponsive, "/", &[]));
+        let elapsed = start.elapsed();
+        assert!(elapsed < Duration::from_millis(800), "{elapsed:?}");
+    }
+
+    #[rstest]
+    fn test_request_trickle() {
+        // Each read succeeds well within the timeout, but the response never ends.
+        let trickle = listen(|mut stream| {
+            read_request(&mut stream);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\n");
+            for _ in 0..600 {
+                thread::sleep(Duration::from_millis(50));
+                if stream.write_all(b"X").is_err() {
+                    break;
+                }
+            }
+        });
+        let client = Client {
+            timeout: Duration::from_millis(300),
+            deadline: Instant::now() + Duration::from_secs(5),
+        };
+
+        let start = Instant::now();
+        assert_eq!(None, client.request("GET", &trickle, "/", &[]));
+        let elapsed = start.elapsed();
+        assert!(elapsed < Duration::from_millis(800), "{elapsed:?}");
+    }
+
+    #[rstest]
+    fn test_environment_detector() {
+        let mock = serve(mock);
+        let closed = closed();
+
+        let detector = EnvironmentDetector::new()
+            .with_probe(FakeProbe::new())
+            .with_imds(config(&closed, &closed, &mock));
+
+        assert_eq!(
+            Some(ComputeEnvironment::GcpComputeEngine),
+            detector.detect_one(MAX_INDIVIDUAL_WEIGHTING)
+        );
+        assert_eq!(
+            None,
+            EnvironmentDetector::new()
+                .with_probe(FakeProbe::new())
+                .detect_one(MAX_INDIVIDUAL_WEIGHTING)
+        );
+    }
+
+    #[rstest]
+    #[case::ip("http://169.254.169.254", Some(("169.254.169.254:80", "")))]
+    #[case::port("http://127.0.0.1:8080/", Some(("127.0.0.1:8080", "")))]
+    #[case::path("http://127.0.0.1:8080/imds/", Some(("127.0.0.1:8080", "/imds")))]
+    #[case::ipv6("http://[fd00:ec2::254]", Some(("[fd00:ec2::254]:80", "")))]
+    #[case::ipv6_port("http://[::1]:8080", Some(("[::1]:8080", "")))]
+    #[case::host_name("http://metadata.google.internal", None)]
+    #[case::localhost("http://localhost:8080", None)]
+    #[case::https("https://169.254.169.254", None)]
+    #[case::invalid_port("http://127.0.0.1:http", None)]
+    #[case::empty("http://", None)]
+    fn test_endpoint_parse(#[case] url: &str, #[case] expected: Option<(&str, &str)>) {
+        let endpoint = Endpoint::parse(url);
+
+        assert_eq!(
+            expected.map(|(address, path)| (address.parse().unwrap(), path)),
+            endpoint.map(|endpoint| (endpoint.address, endpoint.path))
+        );
+    }
+
+    #[rstest]
+    #[case::content_length(
+        "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\ntoken",
+        Some((200, "token"))
+    )]
+    #[case::chunked(
+        "HTTP/1.1 200 OK\r\ntransfer-encoding: Chunked\r\n\r\n5\r\nAzure\r\nB;ext=1\r\nPublicCloud\r\n0\r\n\r\n",
+        Some((200, "AzurePublicCloud"))
+    )]
+    #[case::truncated_chunk(
+        "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n10\r\nAzure",
+        None
+    )]
+    #[case::not_found("HTTP/1.1 404 Not Found\r\n\r\n", Some((404, "")))]
+    #[case::truncated_head("HTTP/1.1 200 OK\r\nContent-Length: 5\r\n", None)]
+    #[case::invalid_status("HTTP/1.1 OK\r\n\r\n", None)]
+    fn test_response_parse(#[case] response: &str, #[case] expected: Option<(u16, &str)>) {
+        let response = Response::parse(response.as_bytes());
+
+        assert_eq!(
+            expected,
+            response.as_ref().map(|response| (
+                response.status,
+                std::str::from_utf8(&response.body).unwrap()
+            ))
+        );
+    }
+}
@@ -3,6 +3,23 @@
 //! This library provides two functions for easily detecting a [`ComputeEnvironment`] based on a
 //! given weighted threshold.
 //!
+//! When the detected environment is unexpected, [`detect_report`] returns a [`DetectionReport`]
+//! describing how each candidate was scored. The inputs of a detection can be captured as a
+//! [`DetectionSnapshot`] and replayed on another machine.
+//!
+//! Detection reads environment variables and system files on every call. [`detect_cached`] keeps
+//! the result for the lifetime of the process instead, until [`refresh`] is called.
+//!
+//! [`detect_stack`] returns the detected environment as separate layers (cloud provider, managed
+//! platform, orchestrator, etc.) through an [`EnvironmentStack`].
+//!
+//! Besides SMBIOS data and environment variables, other detection sources such as container
+//! runtime markers or the CPUID hypervisor leaves produce [`Signal`]s, which complement the score
+//! of the detectors expecting them.
+//!
+//! Additional environments can be detected by registering custom [`Detector`]s on an
+//! [`EnvironmentDetector`].
+//!
 //! # Examples
 //!
 //! ```
@@ -16,20 +33,57 @@
 //! let compute_env = detect_one(MAX_INDIVIDUAL_WEIGHTING);
 //! println!("{:?}", compute_env);
 //! ```
+//!
+//! # Features
+//!
+//! - `cli`: builds the `environment-detector` binary, printing the detected environments.
+//! - `imds`: queries the instance metadata services of the cloud providers when enabled with
+//!   [`EnvironmentDetector::with_imds`], configured through [`ImdsConfig`].
+//! - `opentelemetry`: provides an OpenTelemetry resource detector through
+//!   [`EnvironmentResourceDetector`].
+//! - `serde`: implements `Serialize` and `Deserialize` for [`ComputeEnvironment`],
+//!   [`CloudProvider`], the detection results and [`DetectionSnapshot`].
 
 #![warn(missing_docs)]
 
-use std::{cmp::Ordering, collections::HashSet, ops::Deref};
+use std::cmp::Ordering;
 
+mod cache;
+pub use cache::{detect_cached, refresh};
 mod detector;
-use detector::Detector;
+pub use detector::Detector;
 mod env_vars;
 mod environment;
-pub use environment::{CloudProvider, ComputeEnvironment};
+mod exclusion;
+pub use environment::{CloudProvider, ComputeEnvironment, CustomEnvironment, ParseError};
+pub use exclusion::Exclusion;
+#[cfg(feature = "imds")]
+mod imds;
+#[cfg(feature = "imds")]
+pub use imds::ImdsConfig;
+mod probe;
+pub use probe::{FakeProbe, HostProbe, SystemProbe};
+mod report;
+#[cfg(feature = "opentelemetry")]
+mod resource;
+pub use report::{Candidate, DetectionReport, EnvVarsEvidence, SignalsEvidence, SmbiosEvidence};
+#[cfg(feature = "opentelemetry")]
+pub use resource::EnvironmentResourceDetector;
+#[cfg(feature = "serde")]
+mod serde_impls;
+mod signal;
+pub use signal::{EnvValue, Signal};
 mod smbios;
-use smbios::Smbios;
+pub use smbios::{SmbiosField, SmbiosPattern};
+mod snapshot;
+pub use snapshot::DetectionSnapshot;
+mod sources;
 mod specificity;
+mod stack;
 use specificity::Specificity as _;
+pub use stack::{
+    ContainerRuntime, CpuFamily, EnvironmentStack, Hypervisor, Layer, ManagedPlatform, Orchestrator,
+};
 
 /// Represents the maximum weighting of all supported detectors (`2^15`).
 ///
@@ -39,57 +93,187 @@ pub const MAX_TOTAL_WEIGHTING: u16 = 2 << 14;
 
 /// Represents the maximum individual detector weighting.
 ///
-/// There are currently 2 supported detectors:
+/// There are currently 2 weighted detectors:
 /// - SMBIOS
 /// - Environment Variables
+///
+/// Other [`Signal`]s, such as the container runtime or the hypervisor, raise the score of one of
+/// them up to their own weight, which is at most this maximum.
 pub const MAX_INDIVIDUAL_WEIGHTING: u16 = MAX_TOTAL_WEIGHTING / 2;
 
 /// Detect a single, most likely [`ComputeEnvironment`] above a certain weighted threshold.
 pub fn detect_one(threshold: u16) -> Option<ComputeEnvironment> {
-    detect(threshold).first().copied()
+    EnvironmentDetector::new().detect_one(threshold)
 }
 
 /// Detect potential [`ComputeEnvironment`]s above a certain weighted threshold.
 ///
 /// Returns an ordered [`Vec`] with the highest weighted candidates first.
 pub fn detect(threshold: u16) -> Vec<ComputeEnvironment> {
-    let detectors: Vec<_> = ComputeEnvironment::iter().map(|ce| ce.detector()).collect();
+    EnvironmentDetector::new().detect(threshold)
+}
 
-    // Read current environment variables
-    let env_vars: HashSet<_> = detectors
-        .iter()
-        .flat_map(|detector| detector.env_vars)
-        .filter(|var| env_vars::hasenv(var))
-        .map(Deref::deref)
-        .collect();
+/// Detect potential [`ComputeEnvironment`]s above a certain weighted threshold, alongside the
+/// evidence used to score every candidate.
+pub fn detect_report(threshold: u16) -> DetectionReport {
+    EnvironmentDetector::new().detect_report(threshold)
+}
+
+/// Detect the [`EnvironmentStack`] above a certain weighted threshold.
+pub fn detect_stack(threshold: u16) -> EnvironmentStack {
+    EnvironmentDetector::new().detect_stack(threshold)
+}
+
+/// Configurable detector for [`ComputeEnvironment`]s.
+///
+/// The [`detect`], [`detect_one`] and [`detect_report`] functions use an [`EnvironmentDetector`]
+/// with only the built-in detectors.
+///
+/// # Examples
+///
+/// ```
+/// use apollo_environment_detector::{
+///     ComputeEnvironment, CustomEnvironment, Detector, EnvironmentDetector, SmbiosPattern,
+///     MAX_INDIVIDUAL_WEIGHTING,
+/// };
+///
+/// let detector = EnvironmentDetector::new().with_detector(Detector::new(
+///     ComputeEnvironment::Custom(CustomEnvironment::new("Acme Platform", "acme_platform")),
+///     SmbiosPattern::new().with_sys_vendor("acme"),
+///     &["ACME_NODE_ID", "ACME_REGION"],
+/// ));
+///
+/// let compute_env = detector.detect_one(MAX_INDIVIDUAL_WEIGHTING);
+/// println!("{:?}", compute_env);
+/// ```
+#[derive(Debug, Clone)]
+pub struct EnvironmentDetector<P = HostProbe> {
+    detectors: Vec<Detector>,
+    probe: P,
+    #[cfg(feature = "imds")]
+    imds: Option<ImdsConfig>,
+}
+
+impl EnvironmentDetector {
+    /// Create a new [`EnvironmentDetector`] with all the built-in detectors, reading from the
+    /// current host.
+    pub fn new() -> Self {
+        Self {
+            detectors: ComputeEnvironment::iter().map(|ce| ce.detector()).collect(),
+            probe: HostProbe::new(),
+            #[cfg(feature = "imds")]
+            imds: None,
+        }
+    }
+}
+
+impl<P: SystemProbe> EnvironmentDetector<P> {
+    /// Use a different [`SystemProbe`] to read system information.
+    pub fn with_probe<Q: SystemProbe>(self, probe: Q) -> EnvironmentDetector<Q> {
+        EnvironmentDetector {
+            detectors: self.detectors,
+            probe,
+            #[cfg(feature = "imds")]
+            imds: self.imds,
+        }
+    }
+
+    /// Query the instance metadata services of the cloud providers during detection.
+    ///
+    /// This makes network requests, bounded by the timeouts of the [`ImdsConfig`], on every
+    /// detection. The detected cloud provider is reported as a [`Signal::CloudProvider`].
+    #[cfg(feature = "imds")]
+    pub fn with_imds(self, config: ImdsConfig) -> Self {
+        Self {
+            imds: Some(config),
+            ..self
+        }
+    }
+
+    /// Register an additional [`Detector`].
+    pub fn with_detector(mut self, detector: Detector) -> Self {
+        self.detectors.push(detector);
+        self
+    }
+
+    /// Register additional [`Detector`]s.
+    pub fn with_detectors(mut self, detectors: impl IntoIterator<Item = Detector>) -> Self {
+        self.detectors.extend(detectors);
+        self
+    }
+
+    /// Detect a single, most likely [`ComputeEnvironment`] above a certain weighted threshold.
+    pub fn detect_one(&self, threshold: u16) -> Option<ComputeEnvironment> {
+        self.detect(threshold).first().copied()
+    }
 
-    // Read SMBIOS data
-    let smbios = Smbios::detect();
+    /// Detect potential [`ComputeEnvironment`]s above a certain weighted threshold.
+    ///
+    /// Returns an ordered [`Vec`] with the highest weighted candidates first.
+    pub fn detect(&self, threshold: u16) -> Vec<ComputeEnvironment> {
+        self.detect_report(threshold).environments()
+    }
+
+    /// Detect potential [`ComputeEnvironment`]s above a certain weighted threshold, alongside the
+    /// evidence used to score every candidate.
+    pub fn detect_report(&self, threshold: u16) -> DetectionReport {
+        self.detect_snapshot(&self.snapshot(), threshold)
+    }
+
+    /// Capture the inputs used for detection, as read by the [`SystemProbe`].
+    ///
+    /// Only the environment variables expected by the registered detectors are captured.
+    pub fn snapshot(&self) -> DetectionSnapshot {
+        let snapshot = DetectionSnapshot::read(&self.detectors, &self.probe);
+
+        #[cfg(feature = "imds")]
+        let snapshot = match &self.imds {
+            Some(config) => snapshot.with_signals(imds::detect(config).map(Signal::CloudProvider)),
+            None => snapshot,
+        };
+
+        snapshot
+    }
 
-    // Run detectors against env vars and SMBIOS data
-    detect_inner(detectors, smbios, env_vars, threshold)
+    /// Detect potential [`ComputeEnvironment`]s above a certain weighted threshold from a
+    /// previously captured [`DetectionSnapshot`], alongside the evidence used to score every
+    /// candidate.
+    ///
+    /// The [`SystemProbe`] is not used, so the result only depends on the snapshot and the
+    /// registered detectors.
+    pub fn detect_snapshot(&self, snapshot: &DetectionSnapshot, threshold: u16) -> DetectionReport {
+        detect_inner(&self.detectors, snapshot, threshold)
+    }
+
+    /// Detect the [`EnvironmentStack`] above a certain weighted threshold.
+    pub fn detect_stack(&self, threshold: u16) -> EnvironmentStack {
+        EnvironmentStack::from(&self.detect_report(threshold))
+    }
+}
+
+impl Default for EnvironmentDetector {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 fn detect_inner(
-    detectors: Vec<Detector>,
-    smbios: Smbios,
-    env_vars: HashSet<&'static str>,
+    detectors: &[Detector],
+    snapshot: &DetectionSnapshot,
     threshold: u16,
-) -> Vec<ComputeEnvironment> {
-    let mut detectors: Vec<_> = detectors
-        .into_iter()
-        .filter_map(|detector| {
-            let score = detector.detect(&smbios, &env_vars);
-            if score >= threshold {
-                Some((detector, score))
-            } else {
-                None
-            }
+) -> DetectionReport {
+    let (mut candidates, mut rejected): (Vec<_>, Vec<_>) = detectors
+        .iter()
+        .map(|detector| {
+            let candidate = detector.evaluate(snapshot);
+            (detector, candidate)
         })
-        .collect();
+        .partition(|(_, candidate)| {
+            candidate.exclusions.is_empty() && candidate.score >= threshold
+        });
 
-    detectors.sort_by(|(left, left_score), (right, right_score)| {
-        match Ord::cmp(left_score, right_score) {
+    candidates.sort_by(|(left, left_candidate), (right, right_candidate)| {
+        match Ord::cmp(&left_candidate.score, &right_candidate.score) {
             Ordering::Equal => left
                 .specificity_cmp(right)
                 .unwrap_or(Ordering::Equal)
@@ -97,21 +281,33 @@ fn detect_inner(
             o => o.reverse(),
         }
     });
+    rejected.sort_by(|(_, left), (_, right)| Ord::cmp(&left.score, &right.score).reverse());
 
-    detectors
-        .into_iter()
-        .map(|(detector, _)| detector.environment)
-        .collect()
+    DetectionReport {
+        threshold,
+        signals: snapshot.signals.iter().copied().collect(),
+        candidates: candidates
+            .into_iter()
+            .map(|(_, candidate)| candidate)
+            .collect(),
+        rejected: rejected
+            .into_iter()
+            .map(|(_, candidate)| candidate)
+            .collect(),
+    }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+    use std::{collections::HashSet, ops::Deref};
+
     use rstest::{fixture, rstest};
 
     use super::*;
+    use crate::smbios::Smbios;
 
     #[fixture]
-    fn detectors() -> Vec<Detector> {
+    pub(crate) fn detectors() -> Vec<Detector> {
         ComputeEnvironment::iter().map(|ce| ce.detector()).collect()
     }
 
@@ -150,7 +346,12 @@ mod tests {
             .map(Deref::deref)
             .collect();
 
-        let result = detect_inner(detectors, smbios, env_vars, u16::MIN);
+        let result = detect_inner(
+            &detectors,
+            &DetectionSnapshot::new(smbios, env_vars),
+            u16::MIN,
+        )
+        .environments();
 
         assert_eq!(result.first(), Some(&environment));
     }
@@ -163,10 +364,8 @@ mod tests {
             ComputeEnvironment::AwsLambda,
             ComputeEnvironment::AwsKubernetes,
             ComputeEnvironment::AwsNomad,
-            // Accepted risk: these tests will fail if we remove one of the env var specific to
-            // Azure Container Apps
-            // ComputeEnvironment::AzureContainerApps,
-            // ComputeEnvironment::AzureContainerAppsJob,
+            ComputeEnvironment::AzureContainerApps,
+            ComputeEnvironment::AzureContainerAppsJob,
             ComputeEnvironment::AzureContainerInstance,
             ComputeEnvironment::AzureKubernetes,
             ComputeEnvironment::AzureVM,
@@ -190,9 +389,14 @@ mod tests {
         for i in 0..(env_vars.len()) {
             let mut env_vars = env_vars.clone();
             let removed = env_vars.remove(i);
-            let env_vars = env_vars.into_iter().collect();
+            let env_vars: HashSet<_> = env_vars.into_iter().collect();
 
-            let result = detect_inner(detectors.clone(), smbios.clone(), env_vars, u16::MIN);
+            let result = detect_inner(
+                &detectors,
+                &DetectionSnapshot::new(smbios.clone(), env_vars),
+                u16::MIN,
+            )
+            .environments();
 
             assert_eq!(
                 result.first(),
@@ -210,10 +414,8 @@ mod tests {
             ComputeEnvironment::AwsLambda,
             ComputeEnvironment::AwsKubernetes,
             ComputeEnvironment::AwsNomad,
-            // Accepted risk: these tests will fail if we remove two of the env vars specific to
-            // Azure Container Apps
-            // ComputeEnvironment::AzureContainerApps,
-            // ComputeEnvironment::AzureContainerAppsJob,
+            ComputeEnvironment::AzureContainerApps,
+            ComputeEnvironment::AzureContainerAppsJob,
             ComputeEnvironment::AzureContainerInstance,
             ComputeEnvironment::AzureKubernetes,
             ComputeEnvironment::AzureVM,
@@ -239,9 +441,14 @@ mod tests {
                 let mut env_vars = env_vars.clone();
                 let removed_1 = env_vars.remove(i);
                 let removed_2 = env_vars.remove(j);
-                let env_vars = env_vars.into_iter().collect();
+                let env_vars: HashSet<_> = env_vars.into_iter().collect();
 
-                let result = detect_inner(detectors.clone(), smbios.clone(), env_vars, u16::MIN);
+                let result = detect_inner(
+                    &detectors,
+                    &DetectionSnapshot::new(smbios.clone(), env_vars),
+                    u16::MIN,
+                )
+                .environments();
 
                 assert_eq!(
                     result.first(),
@@ -251,4 +458,229 @@ mod tests {
             }
         }
     }
+
+    #[rstest]
+    fn test_report_threshold(detectors: Vec<Detector>) {
+        let environment = ComputeEnvironment::AwsKubernetes;
+        let smbios: Smbios = environment.detector().smbios.clone().into();
+        let env_vars: HashSet<_> = environment
+            .detector()
+            .env_vars
+            .iter()
+            .map(Deref::deref)
+            .collect();
+
+        let report = detect_inner(
+            &detectors,
+            &DetectionSnapshot::new(smbios, env_vars),
+            MAX_TOTAL_WEIGHTING,
+        );
+
+        assert_eq!(MAX_TOTAL_WEIGHTING, report.threshold);
+        assert_eq!(
+            Some(environment),
+            report.best().map(|candidate| candidate.environment)
+        );
+        assert!(report
+            .candidates
+            .iter()
+            .all(|candidate| candidate.score >= MAX_TOTAL_WEIGHTING));
+        assert!(report
+            .rejected
+            .iter()
+            .all(|candidate| candidate.score < MAX_TOTAL_WEIGHTING));
+        assert!(report
+            .rejected
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+        assert_eq!(
+            ComputeEnvironment::iter().count(),
+            report.candidates.len() + report.rejected.len()
+        );
+    }
+
+    #[rstest]
+    fn test_report_exclusions(detectors: Vec<Detector>) {
+        let env_vars: HashSet<_> = ComputeEnvironment::AzureContainerApps
+            .detector()
+            .env_vars
+            .iter()
+            .map(Deref::deref)
+            .collect();
+
+        let report = detect_inner(
+            &detectors,
+            &DetectionSnapshot::new(Smbios::default(), env_vars),
+            u16::MIN,
+        );
+
+        assert_eq!(
+            Some(ComputeEnvironment::AzureContainerApps),
+            report.best().map(|candidate| candidate.environment)
+        );
+        assert!(report
+            .candidates
+            .iter()
+            .all(|candidate| candidate.environment != ComputeEnvironment::Kubernetes));
+        assert!(report.rejected.iter().any(|candidate| {
+            candidate.environment == ComputeEnvironment::Kubernetes
+                && candidate.score == 0
+                && !candidate.exclusions.is_empty()
+        }));
+    }
+
+    #[rstest]
+    fn test_custom_detector(mut detectors: Vec<Detector>) {
+        let environment =
+            ComputeEnvironment::Custom(CustomEnvironment::new("Custom Nomad", "custom_nomad"));
+        detectors.push(Detector::new(
+            environment,
+            SmbiosPattern::new().with_sys_vendor("custom_vendor"),
+            &["NOMAD_ALLOC_ID", "NOMAD_JOB_NAME", "CUSTOM_ENV"],
+        ));
+
+        let smbios = Smbios::from(SmbiosPattern::new().with_sys_vendor("custom_vendor"));
+        let env_vars = HashSet::from(["NOMAD_ALLOC_ID", "NOMAD_JOB_NAME", "CUSTOM_ENV"]);
+
+        let result = detect_inner(
+            &detectors,
+            &DetectionSnapshot::new(smbios, env_vars),
+            MAX_INDIVIDUAL_WEIGHTING,
+        );
+
+        assert_eq!(vec![environment], result.environments());
+    }
+
+    #[rstest]
+    #[case::aws_lambda(
+        env_vars::AWS_LAMBDA
+            .iter()
+            .fold(FakeProbe::new(), |probe, env_var| probe.with_env(*env_var)),
+        ComputeEnvironment::AwsLambda
+    )]
+    #[case::gcp_kubernetes(
+        FakeProbe::new()
+            .with_file("/sys/class/dmi/id/bios_vendor", "Google\n")
+            .with_file("/sys/class/dmi/id/sys_vendor", "Google\n")
+            .with_env("KUBERNETES_SERVICE_HOST")
+            .with_env("KUBERNETES_SERVICE_PORT")
+            .with_env("KUBERNETES_SERVICE_PORT_HTTPS")
+            .with_env("KUBERNETES_PORT"),
+        ComputeEnvironment::GcpKubernetes
+    )]
+    #[case::qemu(
+        FakeProbe::new().with_dmi(SmbiosField::SysVendor, "QEMU"),
+        ComputeEnvironment::Qemu
+    )]
+    #[case::aws_ec2_xen(
+        FakeProbe::new()
+            .with_dmi(SmbiosField::BiosVendor, "Xen")
+            .with_dmi(SmbiosField::SysVendor, "Xen")
+            .with_dmi(SmbiosField::ProductName, "HVM domU")
+            .with_file("/sys/hypervisor/type", "xen\n")
+            .with_file("/sys/hypervisor/uuid", "ec2e1916-9099-7caf-fd21-012345abcdef\n"),
+        ComputeEnvironment::AwsEc2
+    )]
+    #[case::aws_ecs_fargate(
+        FakeProbe::new().with_env_value("AWS_EXECUTION_ENV", "AWS_ECS_FARGATE"),
+        ComputeEnvironment::AwsEcs
+    )]
+    #[case::azure_vm_cloud_init(
+        FakeProbe::new().with_file("/run/cloud-init/cloud-id", "azure\n"),
+        ComputeEnvironment::AzureVM
+    )]
+    #[case::gcp_compute_engine_cloud_init(
+        FakeProbe::new().with_file(
+            "/run/cloud-init/instance-data.json",
+            r#"{"v1": {"cloud_name": "gce", "region": "us-central1"}}"#
+        ),
+        ComputeEnvironment::GcpComputeEngine
+    )]
+    #[case::aws_ec2_pci(
+        FakeProbe::new()
+            .with_file("/sys/bus/pci/devices/0000:00:04.0/vendor", "0x1d0f\n")
+            .with_file("/sys/bus/pci/devices/0000:00:1f.0/vendor", "0x1d0f\n"),
+        ComputeEnvironment::AwsEc2
+    )]
+    #[case::gcp_compute_engine_mac(
+        FakeProbe::new()
+            .with_file("/sys/class/net/ens4/address", "42:01:0a:80:00:02\n")
+            .with_file("/sys/class/net/ens4/device/vendor", "0x1af4\n"),
+        ComputeEnvironment::GcpComputeEngine
+    )]
+    #[case::aws_ec2_consul_dns(
+        FakeProbe::new()
+            .with_dmi(SmbiosField::SysVendor, "Amazon EC2")
+            .with_file("/etc/resolv.conf", "search service.consul\nnameserver 127.0.0.1\n"),
+        ComputeEnvironment::AwsEc2
+    )]
+    #[case::aws_ec2_ebs(
+        FakeProbe::new()
+            .with_dmi(SmbiosField::SysVendor, "")
+            .with_file("/sys/block/nvme0n1/device/model", "Amazon Elastic Block Store\n"),
+        ComputeEnvironment::AwsEc2
+    )]
+    fn test_probe(#[case] probe: FakeProbe, #[case] expected: ComputeEnvironment) {
+        let result = EnvironmentDetector::new()
+            .with_probe(probe)
+            .detect_one(MAX_INDIVIDUAL_WEIGHTING);
+
+        assert_eq!(Some(expected), result);
+    }
+
+    #[rstest]
+    fn test_cpu_family_hint() {
+        // Graviton3 instances without SMBIOS data.
+        let probe = FakeProbe::new().with_file(
+            "/sys/devices/system/cpu/cpu0/regs/identification/midr_el1",
+            "0x00000000411fd401\n",
+        );
+        let detector = EnvironmentDetector::new().with_probe(probe);
+
+        // The CPU family is only a hint, which ranks AWS first without reaching the threshold.
+        assert_eq!(
+            Some(ComputeEnvironment::AwsEc2),
+            detector.detect_one(u16::MIN)
+        );
+        assert_eq!(None, detector.detect_one(MAX_INDIVIDUAL_WEIGHTING));
+    }
+
+    #[rstest]
+    fn test_kubernetes_without_env_vars(
+        #[values(
+            ComputeEnvironment::AwsKubernetes,
+            ComputeEnvironment::AzureKubernetes,
+            ComputeEnvironment::GcpKubernetes,
+            ComputeEnvironment::Kubernetes
+        )]
+        environment: ComputeEnvironment,
+        #[values(
+            "/var/run/secrets/kubernetes.io/serviceaccount/namespace",
+            "/dev/termination-log"
+        )]
+        file: &str,
+    ) {
+        let smbios = environment.detector().smbios;
+        let probe = SmbiosField::ALL
+            .iter()
+            .filter_map(|field| Some((*field, smbios.get(*field)?)))
+            .fold(FakeProbe::new(), |probe, (field, value)| {
+                probe.with_dmi(field, value)
+            })
+            .with_file(file, "")
+            .with_file(
+                "/proc/self/cgroup",
+                "0::/kubepods.slice/cri-containerd-4b1d6c5a0e7f.scope\n",
+            );
+
+        let report = EnvironmentDetector::new()
+            .with_probe(probe)
+            .detect_report(MAX_INDIVIDUAL_WEIGHTING);
+
+        assert_eq!(Some(environment), report.best().map(|c| c.environment));
+        assert_eq!(
+            MAX_INDIVIDUAL_WEIGHTING,
+            report.best().unwrap().signals.runtime_score
+        );
+    }
 }
@@ -0,0 +1,320 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use crate::{env_vars, smbios, SmbiosField};
+
+/// Source of the system information used during detection.
+///
+/// [`HostProbe`] reads from the current process and host, while [`FakeProbe`] serves in-memory
+/// data, which is useful to test how an application behaves in a given environment.
+///
+/// # Examples
+///
+/// ```
+/// use apollo_environment_detector::{
+///     ComputeEnvironment, EnvironmentDetector, FakeProbe, SmbiosField, MAX_INDIVIDUAL_WEIGHTING,
+/// };
+///
+/// let probe = FakeProbe::new()
+///     .with_dmi(SmbiosField::BiosVendor, "Google")
+///     .with_dmi(SmbiosField::SysVendor, "Google")
+///     .with_env("KUBERNETES_SERVICE_HOST")
+///     .with_env("KUBERNETES_SERVICE_PORT")
+///     .with_env("KUBERNETES_SERVICE_PORT_HTTPS")
+///     .with_env("KUBERNETES_PORT");
+///
+/// let compute_env = EnvironmentDetector::new()
+///     .with_probe(probe)
+///     .detect_one(MAX_INDIVIDUAL_WEIGHTING);
+/// assert_eq!(Some(ComputeEnvironment::GcpKubernetes), compute_env);
+/// ```
+pub trait SystemProbe {
+    /// Returns `true` if the environment variable is set.
+    ///
+    /// Implementations should avoid reading the value of the environment variable, as this data
+    /// should always be treated as secure regardless of the data.
+    fn has_env(&self, name: &str) -> bool;
+
+    /// Returns `true` if the environment variable is set and its value starts with `prefix`.
+    ///
+    /// Implementations should compare the value in place, without copying it. By default, this is
+    /// always `false`.
+    fn env_starts_with(&self, name: &str, prefix: &str) -> bool {
+        let _ = (name, prefix);
+        false
+    }
+
+    /// Returns the content of a file, or `None` if it cannot be read.
+    fn read_file(&self, path: &Path) -> Option<Vec<u8>>;
+
+    /// Returns `true` if the file or directory exists.
+    ///
+    /// This should be preferred over [`SystemProbe::read_file`] when the content doesn't matter,
+    /// as some of these files hold credentials. By default, this checks whether the file can be
+    /// read, which doesn't support directories.
+    fn exists(&self, path: &Path) -> bool {
+        self.read_file(path).is_some()
+    }
+
+    /// Returns the paths of the entries of a directory, or `None` if it cannot be read.
+    ///
+    /// By default, directories can never be read.
+    fn read_dir(&self, path: &Path) -> Option<Vec<PathBuf>> {
+        let _ = path;
+        None
+    }
+
+    /// Returns the value of a SMBIOS field, or `None` if it is unavailable.
+    ///
+    /// By default, this reads the corresponding file under `/sys/class/dmi/id` through
+    /// [`SystemProbe::read_file`].
+    fn dmi(&self, field: SmbiosField) -> Option<String> {
+        smbios::read_dmi_data(self, field)
+    }
+
+    /// Returns the `eax`, `ebx`, `ecx` and `edx` registers set by the CPUID instruction for a
+    /// leaf, or `None` if it is unavailable.
+    ///
+    /// By default, this is always unavailable.
+    fn cpuid(&self, leaf: u32) -> Option<[u32; 4]> {
+        let _ = leaf;
+        None
+    }
+}
+
+/// [`SystemProbe`] reading from the current process and host.
+#[derive(Debug, Default, Clone)]
+pub struct HostProbe {
+    #[cfg(target_os = "windows")]
+    wmi: std::sync::OnceLock<smbios::Smbios>,
+}
+
+impl HostProbe {
+    /// Create a new [`HostProbe`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SystemProbe for HostProbe {
+    fn has_env(&self, name: &str) -> bool {
+        env_vars::hasenv(name)
+    }
+
+    fn env_starts_with(&self, name: &str, prefix: &str) -> bool {
+        env_vars::env_starts_with(name, prefix)
+    }
+
+    fn read_file(&self, path: &Path) -> Option<Vec<u8>> {
+        std::fs::read(path).ok()
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn read_dir(&self, path: &Path) -> Option<Vec<PathBuf>> {
+        let entries = std::fs::read_dir(path).ok()?;
+        Some(
+            entries
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .collect(),
+        )
+    }
+
+    #[cfg(target_os = "windows")]
+    fn dmi(&self, field: SmbiosField) -> Option<String> {
+        self.wmi
+            .get_or_init(smbios::Smbios::detect_wmi)
+            .get(field)
+            .map(ToString::to_string)
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn cpuid(&self, leaf: u32) -> Option<[u32; 4]> {
+        // CPUID is always available on x86_64. The intrinsic is only unsafe on older toolchains.
+        #[allow(unused_unsafe)]
+        let result = unsafe { std::arch::x86_64::__cpuid(leaf) };
+        Some([result.eax, result.ebx, result.ecx, result.edx])
+    }
+}
+
+/// In-memory [`SystemProbe`].
+///
+/// Everything not explicitly set on the [`FakeProbe`] is treated as missing.
+#[derive(Debug, Default, Clone)]
+pub struct FakeProbe {
+    env_vars: HashSet<String>,
+    env_values: HashMap<String, String>,
+    files: HashMap<PathBuf, Vec<u8>>,
+    dmi: HashMap<SmbiosField, String>,
+    cpuid: HashMap<u32, [u32; 4]>,
+}
+
+impl FakeProbe {
+    /// Create an empty [`FakeProbe`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark an environment variable as set.
+    pub fn with_env(mut self, name: impl Into<String>) -> Self {
+        self.env_vars.insert(name.into());
+        self
+    }
+
+    /// Set the value of an environment variable.
+    pub fn with_env_value(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env_values.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set the content of a file.
+    ///
+    /// The parent directories of the file are considered to exist, and list the file.
+    pub fn with_file(mut self, path: impl Into<PathBuf>, content: impl Into<Vec<u8>>) -> Self {
+        self.files.insert(path.into(), content.into());
+        self
+    }
+
+    /// Set the value of a SMBIOS field.
+    ///
+    /// This takes precedence over files set with [`FakeProbe::with_file`].
+    pub fn with_dmi(mut self, field: SmbiosField, value: impl Into<String>) -> Self {
+        self.dmi.insert(field, value.into());
+        self
+    }
+
+    /// Set the `eax`, `ebx`, `ecx` and `edx` registers returned by the CPUID instruction for a
+    /// leaf.
+    pub fn with_cpuid(mut self, leaf: u32, registers: [u32; 4]) -> Self {
+        self.cpuid.insert(leaf, registers);
+        self
+    }
+}
+
+impl SystemProbe for FakeProbe {
+    fn has_env(&self, name: &str) -> bool {
+        self.env_vars.contains(name) || self.env_values.contains_key(name)
+    }
+
+    fn env_starts_with(&self, name: &str, prefix: &str) -> bool {
+        self.env_values
+            .get(name)
+            .is_some_and(|value| value.starts_with(prefix))
+    }
+
+    fn read_file(&self, path: &Path) -> Option<Vec<u8>> {
+        self.files.get(path).cloned()
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.keys().any(|file| file.starts_with(path))
+    }
+
+    fn read_dir(&self, path: &Path) -> Option<Vec<PathBuf>> {
+        let entries: BTreeSet<_> = self
+            .files
+            .keys()
+            .filter_map(|file| file.strip_prefix(path).ok()?.components().next())
+            .map(|entry| path.join(entry))
+            .collect();
+
+        (!entries.is_empty()).then(|| entries.into_iter().collect())
+    }
+
+    fn dmi(&self, field: SmbiosField) -> Option<String> {
+        self.dmi
+            .get(&field)
+            .cloned()
+            .or_else(|| smbios::read_dmi_data(self, field))
+    }
+
+    fn cpuid(&self, leaf: u32) -> Option<[u32; 4]> {
+        self.cpuid.get(&leaf).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use rstest::rstest;
+
+    use super::{FakeProbe, SystemProbe};
+    use crate::SmbiosField;
+
+    #[rstest]
+    fn test_fake_probe_env() {
+        let probe = FakeProbe::new().with_env("TESTING_ENV");
+
+        assert!(probe.has_env("TESTING_ENV"));
+        assert!(!probe.has_env("TESTING_ENV_NOT_SET"));
+    }
+
+    #[rstest]
+    fn test_fake_probe_env_value() {
+        let probe = FakeProbe::new().with_env_value("AWS_EXECUTION_ENV", "AWS_ECS_FARGATE");
+
+        assert!(probe.has_env("AWS_EXECUTION_ENV"));
+        assert!(probe.env_starts_with("AWS_EXECUTION_ENV", "AWS_ECS_"));
+        assert!(!probe.env_starts_with("AWS_EXECUTION_ENV", "AWS_Lambda_"));
+        assert!(!probe.env_starts_with("AWS_EXECUTION_ENV_NOT_SET", ""));
+    }
+
+    #[rstest]
+    #[case::file("/run/secrets/token", true)]
+    #[case::dir("/run/secrets", true)]
+    #[case::partial_name("/run/sec", false)]
+    #[case::missing("/run/other", false)]
+    fn test_fake_probe_exists(#[case] path: &str, #[case] expected: bool) {
+        let probe = FakeProbe::new().with_file("/run/secrets/token", "");
+
+        assert_eq!(expected, probe.exists(Path::new(path)));
+    }
+
+    #[rstest]
+    #[case::files("/run/secrets", Some(vec!["/run/secrets/other", "/run/secrets/token"]))]
+    #[case::dirs("/run", Some(vec!["/run/secrets"]))]
+    #[case::file("/run/secrets/token", None)]
+    #[case::missing("/run/other", None)]
+    fn test_fake_probe_read_dir(#[case] path: &str, #[case] expected: Option<Vec<&str>>) {
+        let probe = FakeProbe::new()
+            .with_file("/run/secrets/token", "")
+            .with_file("/run/secrets/other", "");
+
+        assert_eq!(
+            expected.map(|entries| entries.into_iter().map(PathBuf::from).collect()),
+            probe.read_dir(Path::new(path))
+        );
+    }
+
+    #[rstest]
+    #[case::dmi_only(Some("Amazon EC2"), None, Some("Amazon EC2"))]
+    #[case::file_only(None, Some("Amazon EC2\n"), Some("Amazon EC2"))]
+    #[case::dmi_over_file(Some("Google"), Some("Amazon EC2\n"), Some("Google"))]
+    #[case::empty_file(None, Some("\n"), None)]
+    #[case::missing(None, None, None)]
+    fn test_fake_probe_dmi(
+        #[case] dmi: Option<&str>,
+        #[case] file: Option<&str>,
+        #[case] expected: Option<&str>,
+    ) {
+        let mut probe = FakeProbe::new();
+        if let Some(dmi) = dmi {
+            probe = probe.with_dmi(SmbiosField::SysVendor, dmi);
+        }
+        if let Some(file) = file {
+            probe = probe.with_file("/sys/class/dmi/id/sys_vendor", file);
+        }
+
+        assert_eq!(expected, probe.dmi(SmbiosField::SysVendor).as_deref());
+        assert_eq!(None, probe.dmi(SmbiosField::BiosVendor));
+        assert_eq!(
+            None,
+            probe.read_file(Path::new("/sys/class/dmi/id/bios_vendor"))
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{ComputeEnvironment, Signal, SmbiosField};
+
+/// Detailed result of a detection run.
+///
+/// This contains every [`ComputeEnvironment`] that was considered, alongside the evidence that led
+/// to its score. It is mostly useful for understanding why a given environment was (or wasn't)
+/// detected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct DetectionReport {
+    /// Threshold used for this detection.
+    pub threshold: u16,
+    /// Signals observed by the detection sources other than SMBIOS and environment variables.
+    pub signals: Vec<Signal>,
+    /// Candidates at or above the threshold, with the highest weighted candidates first.
+    ///
+    /// This is the same order as returned by [`detect`](crate::detect).
+    pub candidates: Vec<Candidate>,
+    /// Candidates below the threshold, with the highest weighted candidates first.
+    pub rejected: Vec<Candidate>,
+}
+
+impl DetectionReport {
+    /// Most likely [`Candidate`] at or above the threshold.
+    pub fn best(&self) -> Option<&Candidate> {
+        self.candidates.first()
+    }
+
+    /// Detected [`ComputeEnvironment`]s at or above the threshold, with the highest weighted
+    /// candidates first.
+    pub fn environments(&self) -> Vec<ComputeEnvironment> {
+        self.candidates
+            .iter()
+            .map(|candidate| candidate.environment)
+            .collect()
+    }
+}
+
+/// A single [`ComputeEnvironment`] considered during detection, and how it was scored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Candidate {
+    /// Compute environment for this candidate.
+    pub environment: ComputeEnvironment,
+    /// Total score, from 0 to [`MAX_TOTAL_WEIGHTING`](crate::MAX_TOTAL_WEIGHTING).
+    pub score: u16,
+    /// Evidence obtained from SMBIOS.
+    pub smbios: SmbiosEvidence,
+    /// Evidence obtained from environment variables.
+    pub env_vars: EnvVarsEvidence,
+    /// Evidence obtained from other signals.
+    pub signals: SignalsEvidence,
+    /// Exclusions that were observed. Any of them rules out this candidate, with a score of 0.
+    pub exclusions: Vec<String>,
+}
+
+/// SMBIOS part of a [`Candidate`] score.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct SmbiosEvidence {
+    /// Score from 0 to [`MAX_INDIVIDUAL_WEIGHTING`](crate::MAX_INDIVIDUAL_WEIGHTING).
+    pub score: u16,
+    /// SMBIOS fields matching the expected pattern.
+    pub matched: Vec<SmbiosField>,
+    /// SMBIOS fields that did not match the expected pattern.
+    pub missing: Vec<SmbiosField>,
+    /// SMBIOS fields that could not be read, and are not part of the score.
+    pub unavailable: Vec<SmbiosField>,
+}
+
+/// Environment variables part of a [`Candidate`] score.
+///
+/// This only ever contains the names of environment variables, never their values.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct EnvVarsEvidence {
+    /// Score from 0 to [`MAX_INDIVIDUAL_WEIGHTING`](crate::MAX_INDIVIDUAL_WEIGHTING).
+    pub score: u16,
+    /// Expected environment variables that are set.
+    pub matched: Vec<String>,
+    /// Expected environment variables that are not set.
+    pub missing: Vec<String>,
+}
+
+/// Signals part of a [`Candidate`] score.
+///
+/// The strongest platform signal raises the SMBIOS part of the score up to its weight, and the
+/// strongest other signal raises the environment variables part the same way. Both parts are then
+/// added up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct SignalsEvidence {
+    /// Score of the platform signals, from 0 to
+    /// [`MAX_INDIVIDUAL_WEIGHTING`](crate::MAX_INDIVIDUAL_WEIGHTING), raising the SMBIOS part.
+    pub platform_score: u16,
+    /// Score of the other signals, from 0 to
+    /// [`MAX_INDIVIDUAL_WEIGHTING`](crate::MAX_INDIVIDUAL_WEIGHTING), raising the environment
+    /// variables part.
+    pub runtime_score: u16,
+    /// Expected signals that were observed.
+    pub matched: Vec<Signal>,
+    /// Expected signals that were not observed.
+    pub missing: Vec<Signal>,
+}
@@ -0,0 +1,181 @@
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::resource::{Resource, ResourceDetector};
+
+use crate::{
+    ContainerRuntime, EnvironmentDetector, EnvironmentStack, HostProbe, SystemProbe,
+    MAX_INDIVIDUAL_WEIGHTING,
+};
+
+const CLOUD_PROVIDER: &str = "cloud.provider";
+const CLOUD_PLATFORM: &str = "cloud.platform";
+const CONTAINER_RUNTIME_NAME: &str = "container.runtime.name";
+
+/// OpenTelemetry [`ResourceDetector`] based on the detected
+/// [`ComputeEnvironment`](crate::ComputeEnvironment).
+///
+/// This emits the following attributes for the most likely environment running on a cloud
+/// provider:
+///
+/// * `cloud.provider`, see [`CloudProvider::code`](crate::CloudProvider::code)
+/// * `cloud.platform`, see
+///   [`ComputeEnvironment::platform_code`](crate::ComputeEnvironment::platform_code)
+///
+/// It also emits `container.runtime.name` when running inside a container, whatever the
+/// environment.
+///
+/// Other detected layers are left out, as the semantic conventions have no attribute for them:
+/// `cloud.platform` only has values for the platforms of cloud providers, and there is no attribute
+/// for the orchestrator, the hypervisor or the CPU family. They are available through
+/// [`EnvironmentDetector::detect_stack`] instead.
+///
+/// See <https://opentelemetry.io/docs/specs/semconv/attributes-registry/cloud/> and
+/// <https://opentelemetry.io/docs/specs/semconv/attributes-registry/container/>
+///
+/// # Examples
+///
+/// ```
+/// use apollo_environment_detector::EnvironmentResourceDetector;
+/// use opentelemetry_sdk::Resource;
+///
+/// let resource = Resource::builder()
+///     .with_detector(Box::new(EnvironmentResourceDetector::new()))
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct EnvironmentResourceDetector<P = HostProbe> {
+    detector: EnvironmentDetector<P>,
+    threshold: u16,
+}
+
+impl EnvironmentResourceDetector {
+    /// Create a new [`EnvironmentResourceDetector`] using the built-in detectors, with a
+    /// threshold of [`MAX_INDIVIDUAL_WEIGHTING`].
+    pub fn new() -> Self {
+        Self::from(EnvironmentDetector::new())
+    }
+}
+
+impl<P> EnvironmentResourceDetector<P> {
+    /// Set the detection threshold.
+    pub fn with_threshold(self, threshold: u16) -> Self {
+        Self { threshold, ..self }
+    }
+}
+
+impl Default for EnvironmentResourceDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P> From<EnvironmentDetector<P>> for EnvironmentResourceDetector<P> {
+    fn from(detector: EnvironmentDetector<P>) -> Self {
+        Self {
+            detector,
+            threshold: MAX_INDIVIDUAL_WEIGHTING,
+        }
+    }
+}
+
+impl<P: SystemProbe> ResourceDetector for EnvironmentResourceDetector<P> {
+    fn detect(&self) -> Resource {
+        let mut attributes = Vec::new();
+
+        let report = self.detector.detect_report(self.threshold);
+        if let Some(environment) = report.best().map(|candidate| candidate.environment) {
+            if let Some(cloud_provider) = environment.cloud_provider() {
+                attributes.push(KeyValue::new(CLOUD_PROVIDER, cloud_provider.code()));
+                attributes.push(KeyValue::new(CLOUD_PLATFORM, environment.platform_code()));
+            }
+        }
+
+        let stack = EnvironmentStack::from(&report);
+        if let Some(container_runtime) = stack.container_runtime {
+            attributes.push(KeyValue::new(
+                CONTAINER_RUNTIME_NAME,
+                container_runtime_name(container_runtime.value),
+            ));
+        }
+
+        Resource::builder_empty()
+            .with_attributes(attributes)
+            .build()
+    }
+}
+
+/// Name of the container runtime, as used by the semantic conventions.
+fn container_runtime_name(container_runtime: ContainerRuntime) -> &'static str {
+    match container_runtime {
+        ContainerRuntime::Docker => "docker",
+        ContainerRuntime::Podman => "podman",
+        ContainerRuntime::Containerd => "containerd",
+        ContainerRuntime::CriO => "cri-o",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::{Key, Value};
+    use opentelemetry_sdk::resource::ResourceDetector;
+    use rstest::rstest;
+
+    use super::EnvironmentResourceDetector;
+    use crate::{env_vars, EnvironmentDetector, FakeProbe, SmbiosField};
+
+    #[rstest]
+    fn test_detect() {
+        let probe = env_vars::KUBERNETES.iter().fold(
+            FakeProbe::new()
+                .with_dmi(SmbiosField::BiosVendor, "Google")
+                .with_dmi(SmbiosField::SysVendor, "Google"),
+            |probe, env_var| probe.with_env(*env_var),
+        );
+        let detector =
+            EnvironmentResourceDetector::from(EnvironmentDetector::new().with_probe(probe));
+
+        let resource = detector.detect();
+
+        assert_eq!(
+            Some(Value::from("gcp")),
+            resource.get(&Key::new("cloud.provider"))
+        );
+        assert_eq!(
+            Some(Value::from("gcp_kubernetes_engine")),
+            resource.get(&Key::new("cloud.platform"))
+        );
+        assert_eq!(None, resource.get(&Key::new("container.runtime.name")));
+    }
+
+    #[rstest]
+    fn test_detect_container_runtime() {
+        let probe = env_vars::NOMAD
+            .iter()
+            .fold(FakeProbe::new(), |probe, env_var| probe.with_env(*env_var))
+            .with_file("/.dockerenv", "");
+        let detector =
+            EnvironmentResourceDetector::from(EnvironmentDetector::new().with_probe(probe));
+
+        let resource = detector.detect();
+
+        assert_eq!(None, resource.get(&Key::new("cloud.provider")));
+        assert_eq!(None, resource.get(&Key::new("cloud.platform")));
+        assert_eq!(
+            Some(Value::from("docker")),
+            resource.get(&Key::new("container.runtime.name"))
+        );
+    }
+
+    #[rstest]
+    #[case::not_detected(FakeProbe::new())]
+    #[case::no_cloud_provider(
+        env_vars::NOMAD
+            .iter()
+            .fold(FakeProbe::new(), |probe, env_var| probe.with_env(*env_var))
+    )]
+    fn test_detect_empty(#[case] probe: FakeProbe) {
+        let detector =
+            EnvironmentResourceDetector::from(EnvironmentDetector::new().with_probe(probe));
+
+        assert!(detector.detect().is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+//! Serialization for types that can't derive it.
+//!
+//! [`ComputeEnvironment`] and [`ManagedPlatform`] are represented by their unique identifiers.
+//! Custom environments can be serialized, but never deserialized as they only exist at compile
+//! time.
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{ComputeEnvironment, ManagedPlatform};
+
+impl Serialize for ComputeEnvironment {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.id())
+    }
+}
+
+impl<'de> Deserialize<'de> for ComputeEnvironment {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        Self::from_id(&id).ok_or_else(|| {
+            de::Error::invalid_value(
+                de::Unexpected::Str(&id),
+                &"a compute environment identifier",
+            )
+        })
+    }
+}
+
+impl Serialize for ManagedPlatform {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.compute_environment().id())
+    }
+}
+
+impl<'de> Deserialize<'de> for ManagedPlatform {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        ComputeEnvironment::from_id(&id)
+            .and_then(|ce| ce.platform())
+            .filter(|platform| platform.compute_environment().id() == id)
+            .ok_or_else(|| {
+                de::Error::invalid_value(de::Unexpected::Str(&id), &"a managed platform identifier")
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::{
+        detect_report, CloudProvider, ComputeEnvironment, ContainerRuntime, CpuFamily,
+        CustomEnvironment, DetectionReport, EnvValue, EnvironmentStack, ManagedPlatform, Signal,
+        SmbiosField,
+    };
+
+    #[rstest]
+    fn test_compute_environment_roundtrip() {
+        for environment in ComputeEnvironment::iter() {
+            let json = serde_json::to_string(&environment).unwrap();
+            assert_eq!(format!("\"{}\"", environment.id()), json);
+
+            let deserialized: ComputeEnvironment = serde_json::from_str(&json).unwrap();
+            assert_eq!(environment, deserialized);
+        }
+    }
+
+    #[rstest]
+    fn test_managed_platform_roundtrip() {
+        for platform in ComputeEnvironment::iter().filter_map(|ce| ce.platform()) {
+            let json = serde_json::to_string(&platform).unwrap();
+            let deserialized: ManagedPlatform = serde_json::from_str(&json).unwrap();
+            assert_eq!(platform, deserialized);
+        }
+
+        assert!(serde_json::from_str::<ManagedPlatform>("\"aws_nomad\"").is_err());
+    }
+
+    #[rstest]
+    #[case(ComputeEnvironment::AwsKubernetes, "\"aws_eks\"")]
+    #[case(ComputeEnvironment::AwsNomad, "\"aws_nomad\"")]
+    #[case(ComputeEnvironment::Nomad, "\"nomad\"")]
+    #[case(
+        ComputeEnvironment::Custom(CustomEnvironment::new("Custom", "custom")),
+        "\"custom\""
+    )]
+    fn test_compute_environment_serialize(
+        #[case] environment: ComputeEnvironment,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(expected, serde_json::to_string(&environment).unwrap());
+    }
+
+    #[rstest]
+    #[case("\"custom\"")]
+    #[case("\"AWS EC2\"")]
+    #[case("3")]
+    fn test_compute_environment_deserialize_invalid(#[case] json: &str) {
+        assert!(serde_json::from_str::<ComputeEnvironment>(json).is_err());
+    }
+
+    #[rstest]
+    #[case(CloudProvider::Aws, "\"aws\"")]
+    #[case(CloudProvider::Azure, "\"azure\"")]
+    #[case(CloudProvider::GoogleCloud, "\"gcp\"")]
+    fn test_cloud_provider(#[case] cloud_provider: CloudProvider, #[case] expected: &str) {
+        assert_eq!(expected, serde_json::to_string(&cloud_provider).unwrap());
+        assert_eq!(
+            cloud_provider,
+            serde_json::from_str::<CloudProvider>(expected).unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case(
+        Signal::ContainerRuntime(ContainerRuntime::CriO),
+        r#"{"container_runtime":"crio"}"#
+    )]
+    #[case(
+        Signal::EnvValue(EnvValue::AwsEcsFargate),
+        r#"{"env_value":"aws_ecs_fargate"}"#
+    )]
+    #[case(Signal::Cpu(CpuFamily::NeoverseV2), r#"{"cpu":"neoverse_v2"}"#)]
+    fn test_signal(#[case] signal: Signal, #[case] expected: &str) {
+        assert_eq!(expected, serde_json::to_string(&signal).unwrap());
+        assert_eq!(signal, serde_json::from_str::<Signal>(expected).unwrap());
+    }
+
+    #[rstest]
+    fn test_smbios_field() {
+        for field in SmbiosField::ALL {
+            let json = serde_json::to_string(field).unwrap();
+            assert_eq!(format!("\"{}\"", field.as_str()), json);
+        }
+    }
+
+    #[rstest]
+    fn test_report_roundtrip() {
+        let report = detect_report(u16::MIN);
+        let json = serde_json::to_string(&report).unwrap();
+        let deserialized: DetectionReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(report, deserialized);
+
+        let stack = EnvironmentStack::from(&report);
+        let json = serde_json::to_string(&stack).unwrap();
+        let deserialized: EnvironmentStack = serde_json::from_str(&json).unwrap();
+        assert_eq!(stack, deserialized);
+    }
+}
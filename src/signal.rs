@@ -0,0 +1,231 @@
+use std::fmt::Display;
+
+use crate::{
+    CloudProvider, ContainerRuntime, CpuFamily, Hypervisor, Orchestrator, MAX_INDIVIDUAL_WEIGHTING,
+};
+
+/// Signals of the given expressions, followed by a signal for each container runtime.
+///
+/// Container platforms accept any container runtime, so the runtimes are only listed here.
+macro_rules! with_container_runtimes {
+    ($($signal:expr),* $(,)?) => {
+        &[
+            $($signal,)*
+            Signal::ContainerRuntime(ContainerRuntime::Docker),
+            Signal::ContainerRuntime(ContainerRuntime::Podman),
+            Signal::ContainerRuntime(ContainerRuntime::Containerd),
+            Signal::ContainerRuntime(ContainerRuntime::CriO),
+        ]
+    };
+}
+
+// CPU families only hint at the cloud providers that are their sole large-scale users. Neoverse N1
+// is left out, as it powers both AWS Graviton2 and the Ampere Altra instances of Azure, Google
+// Cloud and Oracle Cloud. So is Cortex-A72, found in AWS Graviton as well as in the Raspberry Pi 4
+// and many other boards.
+pub const AWS: &[Signal] = &[
+    Signal::CloudProvider(CloudProvider::Aws),
+    Signal::Cpu(CpuFamily::NeoverseV1),
+    Signal::Cpu(CpuFamily::NeoverseV2),
+];
+pub const AWS_KUBERNETES: &[Signal] = with_container_runtimes![
+    Signal::CloudProvider(CloudProvider::Aws),
+    Signal::Cpu(CpuFamily::NeoverseV1),
+    Signal::Cpu(CpuFamily::NeoverseV2),
+    Signal::Orchestrator(Orchestrator::Kubernetes),
+];
+pub const AWS_NOMAD: &[Signal] = with_container_runtimes![
+    Signal::CloudProvider(CloudProvider::Aws),
+    Signal::Cpu(CpuFamily::NeoverseV1),
+    Signal::Cpu(CpuFamily::NeoverseV2),
+    Signal::OrchestratorHint(Orchestrator::Nomad),
+];
+pub const AZURE: &[Signal] = &[
+    Signal::CloudProvider(CloudProvider::Azure),
+    Signal::Hypervisor(Hypervisor::HyperV),
+    Signal::Cpu(CpuFamily::NeoverseN2),
+];
+pub const AZURE_CONTAINER: &[Signal] = with_container_runtimes![
+    Signal::CloudProvider(CloudProvider::Azure),
+    Signal::Hypervisor(Hypervisor::HyperV),
+    Signal::Cpu(CpuFamily::NeoverseN2),
+];
+pub const AZURE_KUBERNETES: &[Signal] = with_container_runtimes![
+    Signal::CloudProvider(CloudProvider::Azure),
+    Signal::Hypervisor(Hypervisor::HyperV),
+    Signal::Cpu(CpuFamily::NeoverseN2),
+    Signal::Orchestrator(Orchestrator::Kubernetes),
+];
+pub const AZURE_NOMAD: &[Signal] = with_container_runtimes![
+    Signal::CloudProvider(CloudProvider::Azure),
+    Signal::Hypervisor(Hypervisor::HyperV),
+    Signal::Cpu(CpuFamily::NeoverseN2),
+    Signal::OrchestratorHint(Orchestrator::Nomad),
+];
+pub const GCP: &[Signal] = &[
+    Signal::CloudProvider(CloudProvider::GoogleCloud),
+    Signal::Cpu(CpuFamily::NeoverseV2),
+];
+pub const GCP_KUBERNETES: &[Signal] = with_container_runtimes![
+    Signal::CloudProvider(CloudProvider::GoogleCloud),
+    Signal::Cpu(CpuFamily::NeoverseV2),
+    Signal::Orchestrator(Orchestrator::Kubernetes),
+];
+pub const GCP_NOMAD: &[Signal] = with_container_runtimes![
+    Signal::CloudProvider(CloudProvider::GoogleCloud),
+    Signal::Cpu(CpuFamily::NeoverseV2),
+    Signal::OrchestratorHint(Orchestrator::Nomad),
+];
+pub const AWS_ECS: &[Signal] = with_container_runtimes![
+    Signal::EnvValue(EnvValue::AwsEcsFargate),
+    Signal::EnvValue(EnvValue::AwsEcsEc2),
+];
+pub const AWS_LAMBDA: &[Signal] = &[Signal::EnvValue(EnvValue::AwsLambda)];
+pub const CONTAINER: &[Signal] = with_container_runtimes![];
+pub const EMPTY: &[Signal] = &[];
+pub const KUBERNETES: &[Signal] =
+    with_container_runtimes![Signal::Orchestrator(Orchestrator::Kubernetes)];
+pub const NOMAD: &[Signal] =
+    with_container_runtimes![Signal::OrchestratorHint(Orchestrator::Nomad)];
+pub const QEMU: &[Signal] = &[
+    Signal::Hypervisor(Hypervisor::Qemu),
+    Signal::Hypervisor(Hypervisor::Kvm),
+];
+
+/// Evidence observed on the system, other than SMBIOS data and environment variables.
+///
+/// Signals complement a [`Detector`](crate::Detector): signals about the platform, such as the
+/// hypervisor, raise the SMBIOS part of the score to the weight of the strongest expected one
+/// that was observed, if higher. Other signals do the same for the environment variables part.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum Signal {
+    /// Running inside a container managed by this runtime.
+    ContainerRuntime(ContainerRuntime),
+    /// Running as a workload of this orchestrator.
+    Orchestrator(Orchestrator),
+    /// Running under this hypervisor.
+    Hypervisor(Hypervisor),
+    /// Running on an instance of this cloud provider.
+    CloudProvider(CloudProvider),
+    /// Environment variable set to a value identifying the platform.
+    EnvValue(EnvValue),
+    /// Running on a CPU of this family.
+    Cpu(CpuFamily),
+    /// Configured as workloads of this orchestrator usually are, which is also found elsewhere.
+    OrchestratorHint(Orchestrator),
+}
+
+impl Signal {
+    /// Weight of this signal, from 0 to [`MAX_INDIVIDUAL_WEIGHTING`].
+    pub(crate) fn weight(&self) -> u16 {
+        match self {
+            // Containers run almost anywhere, so this is only a hint towards container platforms.
+            Self::ContainerRuntime(_) => MAX_INDIVIDUAL_WEIGHTING / 4,
+            Self::Orchestrator(_) => MAX_INDIVIDUAL_WEIGHTING,
+            // QEMU only reports itself when emulating the CPU, while the other hypervisors back
+            // many different platforms.
+            Self::Hypervisor(Hypervisor::Qemu) => MAX_INDIVIDUAL_WEIGHTING,
+            Self::Hypervisor(_) => MAX_INDIVIDUAL_WEIGHTING / 4,
+            Self::CloudProvider(_) => MAX_INDIVIDUAL_WEIGHTING,
+            Self::EnvValue(_) => MAX_INDIVIDUAL_WEIGHTING,
+            // CPU families are shared by several cloud providers and other vendors.
+            Self::Cpu(_) => MAX_INDIVIDUAL_WEIGHTING / 4,
+            // Such configurations are shared with other platforms.
+            Self::OrchestratorHint(_) => MAX_INDIVIDUAL_WEIGHTING / 4,
+        }
+    }
+
+    /// Returns `true` if this signal is about the platform, rather than the workload running on
+    /// it.
+    pub(crate) fn is_platform(&self) -> bool {
+        matches!(
+            self,
+            Self::Hypervisor(_) | Self::CloudProvider(_) | Self::Cpu(_)
+        )
+    }
+
+    pub(crate) fn container_runtime(&self) -> Option<ContainerRuntime> {
+        match self {
+            Self::ContainerRuntime(container_runtime) => Some(*container_runtime),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn hypervisor(&self) -> Option<Hypervisor> {
+        match self {
+            Self::Hypervisor(hypervisor) => Some(*hypervisor),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn cpu_family(&self) -> Option<CpuFamily> {
+        match self {
+            Self::Cpu(cpu_family) => Some(*cpu_family),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Signal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ContainerRuntime(container_runtime) => {
+                write!(f, "{container_runtime} (container runtime)")
+            }
+            Self::Orchestrator(orchestrator) => write!(f, "{orchestrator} (orchestrator)"),
+            Self::Hypervisor(hypervisor) => write!(f, "{hypervisor} (hypervisor)"),
+            Self::CloudProvider(cloud_provider) => write!(f, "{cloud_provider} (cloud provider)"),
+            Self::EnvValue(env_value) => write!(f, "{env_value} (environment variable)"),
+            Self::Cpu(cpu_family) => write!(f, "{cpu_family} (CPU)"),
+            Self::OrchestratorHint(orchestrator) => {
+                write!(f, "{orchestrator} (orchestrator hint)")
+            }
+        }
+    }
+}
+
+/// Value of an environment variable identifying a platform.
+///
+/// Values are compared in place, and never copied. Only the matching pattern is ever stored.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum EnvValue {
+    /// `AWS_EXECUTION_ENV` set to `AWS_ECS_FARGATE`.
+    AwsEcsFargate,
+    /// `AWS_EXECUTION_ENV` set to `AWS_ECS_EC2`.
+    AwsEcsEc2,
+    /// `AWS_EXECUTION_ENV` set to `AWS_Lambda_<runtime>`.
+    AwsLambda,
+}
+
+impl EnvValue {
+    pub(crate) const ALL: &'static [Self] =
+        &[Self::AwsEcsFargate, Self::AwsEcsEc2, Self::AwsLambda];
+
+    /// Name of the environment variable.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::AwsEcsFargate | Self::AwsEcsEc2 | Self::AwsLambda => "AWS_EXECUTION_ENV",
+        }
+    }
+
+    /// Prefix of the value of the environment variable.
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            Self::AwsEcsFargate => "AWS_ECS_FARGATE",
+            Self::AwsEcsEc2 => "AWS_ECS_EC2",
+            Self::AwsLambda => "AWS_Lambda_",
+        }
+    }
+}
+
+impl Display for EnvValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}*", self.name(), self.prefix())
+    }
+}
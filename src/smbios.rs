@@ -1,20 +1,34 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, collections::BTreeMap, fmt::Display, path::Path};
 
 use crate::{
+    probe::SystemProbe,
+    report::SmbiosEvidence,
     specificity::{OrderingExt, Specificity},
     MAX_INDIVIDUAL_WEIGHTING,
 };
 
+mod table;
+
 pub const AWS: SmbiosPattern = SmbiosPattern::new()
     .with_bios_vendor("amazon")
-    .with_sys_vendor("amazon");
+    .with_sys_vendor("amazon")
+    .with_board_vendor("amazon")
+    .with_chassis_vendor("amazon")
+    .with_product_uuid("ec2");
 pub const AZURE: SmbiosPattern = SmbiosPattern::new()
     .with_bios_vendor("microsoft")
-    .with_sys_vendor("microsoft");
+    .with_sys_vendor("microsoft")
+    .with_board_vendor("microsoft")
+    .with_chassis_vendor("microsoft")
+    // Well-known asset tag set on all Azure VMs, unlike other Hyper-V VMs.
+    .with_chassis_asset_tag("7783-7084-3265-9085-8269-3286-77")
+    .with_oem_string(table::HYPER_V_OEM_STRING);
 pub const EMPTY: SmbiosPattern = SmbiosPattern::new();
 pub const GCP: SmbiosPattern = SmbiosPattern::new()
     .with_bios_vendor("google")
-    .with_sys_vendor("google");
+    .with_sys_vendor("google")
+    .with_board_vendor("google")
+    .with_chassis_vendor("google");
 pub const QEMU: SmbiosPattern = SmbiosPattern::new().with_sys_vendor("qemu");
 
 #[cfg(test)]
@@ -23,36 +37,135 @@ pub const TESTING: SmbiosPattern = SmbiosPattern::new()
     .with_product_name("test_product_name")
     .with_sys_vendor("test_sys_vendor");
 
-/// Represents data obtained from SMBIOS.
-#[derive(Debug, Default, Clone)]
-pub struct Smbios {
-    bios_vendor: Option<String>,
-    product_name: Option<String>,
-    sys_vendor: Option<String>,
+/// Fields read from SMBIOS that can be matched by a detector.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum SmbiosField {
+    /// BIOS vendor
+    BiosVendor,
+    /// System product name
+    ProductName,
+    /// System vendor
+    SysVendor,
+    /// System product version
+    ProductVersion,
+    /// System product family
+    ProductFamily,
+    /// System UUID
+    ///
+    /// Only the first group of the UUID is kept, which is enough to identify some providers
+    /// without identifying the machine. This is usually only readable by root.
+    ProductUuid,
+    /// Baseboard vendor
+    BoardVendor,
+    /// Baseboard product name
+    BoardName,
+    /// Chassis vendor
+    ChassisVendor,
+    /// Chassis asset tag
+    ChassisAssetTag,
+    /// Known markers found in the OEM strings, one per line
+    ///
+    /// These are only available from the raw SMBIOS tables. Other OEM strings are discarded, as
+    /// they may hold secrets.
+    OemStrings,
+}
+
+impl SmbiosField {
+    pub(crate) const ALL: &'static [Self] = &[
+        Self::BiosVendor,
+        Self::ProductName,
+        Self::SysVendor,
+        Self::ProductVersion,
+        Self::ProductFamily,
+        Self::ProductUuid,
+        Self::BoardVendor,
+        Self::BoardName,
+        Self::ChassisVendor,
+        Self::ChassisAssetTag,
+        Self::OemStrings,
+    ];
+
+    /// Static str representation of the [`SmbiosField`].
+    ///
+    /// This corresponds to the attribute name under `/sys/class/dmi/id` on Linux, except for
+    /// [`SmbiosField::OemStrings`].
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::BiosVendor => "bios_vendor",
+            Self::ProductName => "product_name",
+            Self::SysVendor => "sys_vendor",
+            Self::ProductVersion => "product_version",
+            Self::ProductFamily => "product_family",
+            Self::ProductUuid => "product_uuid",
+            Self::BoardVendor => "board_vendor",
+            Self::BoardName => "board_name",
+            Self::ChassisVendor => "chassis_vendor",
+            Self::ChassisAssetTag => "chassis_asset_tag",
+            Self::OemStrings => "oem_strings",
+        }
+    }
+
+    /// Returns `true` if the value read from SMBIOS matches a lowercase pattern.
+    ///
+    /// The UUID is matched on its prefix, as any short pattern could otherwise appear in a random
+    /// UUID. OEM strings match if any of them contains the pattern, and other fields match if they
+    /// contain the pattern.
+    pub(crate) fn matches(&self, value: &str, pattern: &str) -> bool {
+        let value = value.to_lowercase();
+        match self {
+            Self::ProductUuid => value.starts_with(pattern),
+            Self::OemStrings => value.lines().any(|line| line.contains(pattern)),
+            _ => value.contains(pattern),
+        }
+    }
 }
 
+impl Display for SmbiosField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Represents data obtained from SMBIOS.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Smbios(BTreeMap<SmbiosField, String>);
+
 impl Smbios {
-    #[cfg(target_os = "linux")]
-    pub fn detect() -> Self {
-        Self {
-            bios_vendor: read_dmi_data("/sys/class/dmi/id/bios_vendor"),
-            product_name: read_dmi_data("/sys/class/dmi/id/product_name"),
-            sys_vendor: read_dmi_data("/sys/class/dmi/id/sys_vendor"),
+    pub fn get(&self, field: SmbiosField) -> Option<&str> {
+        self.0.get(&field).map(String::as_str)
+    }
+
+    pub fn detect(probe: &(impl SystemProbe + ?Sized)) -> Self {
+        let mut fields: BTreeMap<_, _> = SmbiosField::ALL
+            .iter()
+            .filter_map(|field| Some((*field, probe.dmi(*field)?)))
+            .collect();
+
+        // The raw tables fill the fields that are missing from the probe.
+        for (field, value) in table::read(probe) {
+            fields.entry(field).or_insert(value);
         }
+
+        if let Some(uuid) = fields.get_mut(&SmbiosField::ProductUuid) {
+            uuid.truncate(uuid.find('-').unwrap_or(uuid.len()));
+        }
+
+        Self(fields)
     }
 
     #[cfg(target_os = "windows")]
-    pub fn detect() -> Self {
-        use serde::Deserialize;
-        use wmi::{COMLibrary, WMIConnection};
-
-        #[derive(Deserialize)]
-        #[serde(rename = "Win32_ComputerSystemProduct")]
-        #[serde(rename_all = "PascalCase")]
-        struct ComputerSystemProduct {
-            name: String,
-            vendor: String,
-        }
+    pub fn detect_wmi() -> Self {
+        use std::collections::HashMap;
+
+        use wmi::{COMLibrary, Variant, WMIConnection};
+
+        // Raw rows avoid deriving `Deserialize`, so that serde remains an optional dependency.
+        type ComputerSystemProduct = HashMap<String, Variant>;
 
         let Ok(com) = COMLibrary::new() else {
             return Self::default();
@@ -62,117 +175,164 @@ impl Smbios {
             return Self::default();
         };
 
-        let Ok::<Vec<ComputerSystemProduct>, _>(results) = wmi_con.query() else {
+        let Ok::<Vec<ComputerSystemProduct>, _>(results) =
+            wmi_con.raw_query("SELECT Name, Vendor FROM Win32_ComputerSystemProduct")
+        else {
             return Self::default();
         };
 
-        let Some(product) = results.get(0) else {
+        let Some(product) = results.first() else {
             return Self::default();
         };
 
-        Self {
-            bios_vendor: Some(product.vendor.trim().to_lowercase()),
-            product_name: Some(product.name.trim().to_lowercase()),
-            sys_vendor: None,
-        }
-    }
+        let property = |name| match product.get(name) {
+            Some(Variant::String(value)) => Some(value.trim().to_lowercase()),
+            _ => None,
+        };
 
-    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
-    pub fn detect() -> Self {
-        Self::default()
+        Self(
+            [
+                (SmbiosField::BiosVendor, property("Vendor")),
+                (SmbiosField::ProductName, property("Name")),
+            ]
+            .into_iter()
+            .filter_map(|(field, value)| Some((field, value?)))
+            .collect(),
+        )
     }
 }
 
 impl From<SmbiosPattern> for Smbios {
     fn from(value: SmbiosPattern) -> Self {
-        Self {
-            bios_vendor: value.bios_vendor.map(ToString::to_string),
-            product_name: value.product_name.map(ToString::to_string),
-            sys_vendor: value.sys_vendor.map(ToString::to_string),
-        }
+        Self(
+            SmbiosField::ALL
+                .iter()
+                .filter_map(|field| Some((*field, value.get(*field)?.to_string())))
+                .collect(),
+        )
     }
 }
 
 // Attempts to read dmi data from sysfs.
 //
 // Returns `None` on error.
-#[cfg(target_os = "linux")]
-fn read_dmi_data(path: &str) -> Option<String> {
-    let bytes = std::fs::read(path).ok()?;
+pub(crate) fn read_dmi_data(
+    probe: &(impl SystemProbe + ?Sized),
+    field: SmbiosField,
+) -> Option<String> {
+    let path = Path::new("/sys/class/dmi/id").join(field.as_str());
+    let bytes = probe.read_file(&path)?;
     let data = String::from_utf8(bytes).ok()?;
+    let data = data.trim();
     if data.is_empty() {
         None
     } else {
-        Some(data.trim().to_string())
+        Some(data.to_string())
     }
 }
 
+/// Pattern matched against the SMBIOS data of the current system.
+///
+/// Each field is matched if the SMBIOS value contains the pattern value, ignoring case. Pattern
+/// values must therefore be lowercase.
+///
+/// The score is the share of matching fields, among the fields that could be read from SMBIOS.
+/// Fields that are unavailable, for example because they require elevated permissions, don't
+/// lower the score.
 #[derive(Default, Debug, Clone)]
 pub struct SmbiosPattern {
     bios_vendor: Option<&'static str>,
     product_name: Option<&'static str>,
     sys_vendor: Option<&'static str>,
+    product_version: Option<&'static str>,
+    product_family: Option<&'static str>,
+    product_uuid: Option<&'static str>,
+    board_vendor: Option<&'static str>,
+    board_name: Option<&'static str>,
+    chassis_vendor: Option<&'static str>,
+    chassis_asset_tag: Option<&'static str>,
+    oem_strings: Option<&'static str>,
 }
 
 impl SmbiosPattern {
     /// Returns a score from 0-16384 representing the weight of the detected matches from SMBIOS
     /// information.
-    pub fn detect(&self, smbios: &Smbios) -> u16 {
-        let mut total = 0;
-        let mut found = 0;
-        if let Some(bios_vendor) = self.bios_vendor {
-            total += 1;
-            if smbios
-                .bios_vendor
-                .as_ref()
-                .map(|detected_vendor| detected_vendor.to_lowercase().contains(bios_vendor))
-                .unwrap_or(false)
-            {
-                found += 1;
-            }
-        }
-
-        if let Some(product_name) = self.product_name {
-            total += 1;
-            if smbios
-                .product_name
-                .as_ref()
-                .map(|detected_vendor| detected_vendor.to_lowercase().contains(product_name))
-                .unwrap_or(false)
-            {
-                found += 1;
-            }
-        }
+    #[cfg(test)]
+    pub(crate) fn detect(&self, smbios: &Smbios) -> u16 {
+        self.evaluate(smbios).score
+    }
 
-        if let Some(sys_vendor) = self.sys_vendor {
-            total += 1;
-            if smbios
-                .sys_vendor
-                .as_ref()
-                .map(|detected_vendor| detected_vendor.to_lowercase().contains(sys_vendor))
-                .unwrap_or(false)
-            {
-                found += 1;
+    /// Returns a score from 0-16384 representing the weight of the detected matches from SMBIOS
+    /// information, alongside which fields matched.
+    pub(crate) fn evaluate(&self, smbios: &Smbios) -> SmbiosEvidence {
+        let mut matched = Vec::new();
+        let mut missing = Vec::new();
+        let mut unavailable = Vec::new();
+        for field in SmbiosField::ALL {
+            let Some(pattern) = self.get(*field) else {
+                continue;
+            };
+
+            match smbios.get(*field) {
+                Some(detected) if field.matches(detected, pattern) => matched.push(*field),
+                Some(_) => missing.push(*field),
+                None => unavailable.push(*field),
             }
         }
 
-        if total == 0 {
+        let total = matched.len() + missing.len();
+        let score = match (matched.len() * MAX_INDIVIDUAL_WEIGHTING as usize).checked_div(total) {
+            Some(score) => score as u16,
+            // Nothing to compare with.
+            None if !unavailable.is_empty() => 0,
             // Half of the max individual weigh for a single detector to avoid giving too much weight
             // to empty matches.
-            MAX_INDIVIDUAL_WEIGHTING / 2
-        } else {
-            found * MAX_INDIVIDUAL_WEIGHTING / total
+            None => MAX_INDIVIDUAL_WEIGHTING / 2,
+        };
+
+        SmbiosEvidence {
+            score,
+            matched,
+            missing,
+            unavailable,
         }
     }
 
+    /// Expected value for a given SMBIOS field, if any.
+    pub fn get(&self, field: SmbiosField) -> Option<&'static str> {
+        match field {
+            SmbiosField::BiosVendor => self.bios_vendor,
+            SmbiosField::ProductName => self.product_name,
+            SmbiosField::SysVendor => self.sys_vendor,
+            SmbiosField::ProductVersion => self.product_version,
+            SmbiosField::ProductFamily => self.product_family,
+            SmbiosField::ProductUuid => self.product_uuid,
+            SmbiosField::BoardVendor => self.board_vendor,
+            SmbiosField::BoardName => self.board_name,
+            SmbiosField::ChassisVendor => self.chassis_vendor,
+            SmbiosField::ChassisAssetTag => self.chassis_asset_tag,
+            SmbiosField::OemStrings => self.oem_strings,
+        }
+    }
+
+    /// Create an empty pattern.
     pub const fn new() -> Self {
         Self {
             bios_vendor: None,
             product_name: None,
             sys_vendor: None,
+            product_version: None,
+            product_family: None,
+            product_uuid: None,
+            board_vendor: None,
+            board_name: None,
+            chassis_vendor: None,
+            chassis_asset_tag: None,
+            oem_strings: None,
         }
     }
 
+    /// Match the BIOS vendor.
     pub const fn with_bios_vendor(self, bios_vendor: &'static str) -> Self {
         Self {
             bios_vendor: Some(bios_vendor),
@@ -180,7 +340,7 @@ impl SmbiosPattern {
         }
     }
 
-    #[allow(unused)]
+    /// Match the system product name.
     pub const fn with_product_name(self, product_name: &'static str) -> Self {
         Self {
             product_name: Some(product_name),
@@ -188,23 +348,90 @@ impl SmbiosPattern {
         }
     }
 
+    /// Match the system vendor.
     pub const fn with_sys_vendor(self, sys_vendor: &'static str) -> Self {
         Self {
             sys_vendor: Some(sys_vendor),
             ..self
         }
     }
+
+    /// Match the system product version.
+    pub const fn with_product_version(self, product_version: &'static str) -> Self {
+        Self {
+            product_version: Some(product_version),
+            ..self
+        }
+    }
+
+    /// Match the system product family.
+    pub const fn with_product_family(self, product_family: &'static str) -> Self {
+        Self {
+            product_family: Some(product_family),
+            ..self
+        }
+    }
+
+    /// Match the prefix of the system UUID.
+    pub const fn with_product_uuid(self, product_uuid: &'static str) -> Self {
+        Self {
+            product_uuid: Some(product_uuid),
+            ..self
+        }
+    }
+
+    /// Match the baseboard vendor.
+    pub const fn with_board_vendor(self, board_vendor: &'static str) -> Self {
+        Self {
+            board_vendor: Some(board_vendor),
+            ..self
+        }
+    }
+
+    /// Match the baseboard product name.
+    pub const fn with_board_name(self, board_name: &'static str) -> Self {
+        Self {
+            board_name: Some(board_name),
+            ..self
+        }
+    }
+
+    /// Match the chassis vendor.
+    pub const fn with_chassis_vendor(self, chassis_vendor: &'static str) -> Self {
+        Self {
+            chassis_vendor: Some(chassis_vendor),
+            ..self
+        }
+    }
+
+    /// Match the chassis asset tag.
+    pub const fn with_chassis_asset_tag(self, chassis_asset_tag: &'static str) -> Self {
+        Self {
+            chassis_asset_tag: Some(chassis_asset_tag),
+            ..self
+        }
+    }
+
+    /// Match any of the OEM strings.
+    ///
+    /// Only the Hyper-V certificate string (`[ms_vm_cert/sha1/9b80ca0d5dd061ec9da4e494f4c3fd1196270c22]`)
+    /// and the `xen` string of the Xen `hvmloader` are kept from the OEM strings, so other patterns
+    /// never match. Firecracker doesn't expose SMBIOS tables, and OpenStack Nova only sets the
+    /// system information fields, so neither can be matched here.
+    pub const fn with_oem_string(self, oem_string: &'static str) -> Self {
+        Self {
+            oem_strings: Some(oem_string),
+            ..self
+        }
+    }
 }
 
 impl Specificity for SmbiosPattern {
     fn specificity_cmp(&self, other: &Self) -> Option<Ordering> {
-        let bios_vendor = self.bios_vendor.specificity_cmp(&other.bios_vendor);
-        let product_name = self.product_name.specificity_cmp(&other.product_name);
-        let sys_vendor = self.sys_vendor.specificity_cmp(&other.sys_vendor);
-
-        bios_vendor
-            .merge_specificity(product_name)
-            .merge_specificity(sys_vendor)
+        SmbiosField::ALL
+            .iter()
+            .map(|field| self.get(*field).specificity_cmp(&other.get(*field)))
+            .fold(Some(Ordering::Equal), OrderingExt::merge_specificity)
     }
 }
 
@@ -214,7 +441,8 @@ mod tests {
 
     use crate::MAX_INDIVIDUAL_WEIGHTING;
 
-    use super::{Smbios, SmbiosPattern};
+    use super::{Smbios, SmbiosField, SmbiosPattern, AWS, AZURE};
+    use crate::FakeProbe;
 
     #[rstest]
     #[case::match_none("", "", "", 0)]
@@ -254,4 +482,124 @@ mod tests {
         let detected = SmbiosPattern::new().detect(&smbios);
         assert_eq!(MAX_INDIVIDUAL_WEIGHTING / 2, detected);
     }
+
+    #[rstest]
+    #[case::prefix("EC2E1916", true)]
+    #[case::lowercase("ec2e1916", true)]
+    #[case::not_prefix("1EC2E916", false)]
+    fn test_smbiospattern_product_uuid(#[case] product_uuid: &'static str, #[case] expected: bool) {
+        let smbios = Smbios::from(SmbiosPattern::new().with_product_uuid(product_uuid));
+
+        let evidence = SmbiosPattern::new()
+            .with_product_uuid("ec2")
+            .evaluate(&smbios);
+
+        assert_eq!(expected, evidence.matched == [SmbiosField::ProductUuid]);
+    }
+
+    #[rstest]
+    #[case::nitro(
+        FakeProbe::new()
+            .with_dmi(SmbiosField::BiosVendor, "Amazon EC2")
+            .with_dmi(SmbiosField::SysVendor, "Amazon EC2")
+            .with_dmi(SmbiosField::BoardVendor, "Amazon EC2")
+            .with_dmi(SmbiosField::ChassisVendor, "Amazon EC2"),
+        AWS,
+        MAX_INDIVIDUAL_WEIGHTING
+    )]
+    #[case::xen_root(
+        FakeProbe::new()
+            .with_dmi(SmbiosField::BiosVendor, "Xen")
+            .with_dmi(SmbiosField::SysVendor, "Xen")
+            .with_dmi(SmbiosField::ProductUuid, "EC2E1916-9099-7CAF-FD21-012345678901"),
+        AWS,
+        MAX_INDIVIDUAL_WEIGHTING / 3
+    )]
+    #[case::azure(
+        FakeProbe::new()
+            .with_dmi(SmbiosField::BiosVendor, "American Megatrends Inc.")
+            .with_dmi(SmbiosField::SysVendor, "Microsoft Corporation")
+            .with_dmi(SmbiosField::ChassisAssetTag, "7783-7084-3265-9085-8269-3286-77"),
+        AZURE,
+        MAX_INDIVIDUAL_WEIGHTING / 3 * 2
+    )]
+    #[case::hyper_v(
+        FakeProbe::new()
+            .with_dmi(SmbiosField::BiosVendor, "American Megatrends Inc.")
+            .with_dmi(SmbiosField::SysVendor, "Microsoft Corporation")
+            .with_dmi(SmbiosField::ChassisAssetTag, "0123-4567-8901-2345-6789-0123-45"),
+        AZURE,
+        MAX_INDIVIDUAL_WEIGHTING / 3
+    )]
+    #[case::azure_table(
+        FakeProbe::new()
+            .with_file(
+                "/sys/firmware/dmi/tables/smbios_entry_point",
+                include_bytes!("tests/dmi/azure/smbios_entry_point").as_slice(),
+            )
+            .with_file(
+                "/sys/firmware/dmi/tables/DMI",
+                include_bytes!("tests/dmi/azure/DMI").as_slice(),
+            ),
+        AZURE,
+        MAX_INDIVIDUAL_WEIGHTING
+    )]
+    #[case::unavailable(FakeProbe::new(), AWS, 0)]
+    fn test_smbiospattern_extended_fields(
+        #[case] probe: FakeProbe,
+        #[case] pattern: SmbiosPattern,
+        #[case] expected: u16,
+    ) {
+        let smbios = Smbios::detect(&probe);
+
+        assert_eq!(expected, pattern.detect(&smbios));
+    }
+
+    #[rstest]
+    #[case::hyper_v("[MS_VM_CERT/SHA1/9b80ca0d5dd061ec9da4e494f4c3fd1196270c22]", true)]
+    #[case::other("[MS_VM_CERT/SHA1/27d66596a61c48dd3dc7216fd715126e33f59ae7]", false)]
+    fn test_smbiospattern_oem_strings(#[case] oem_strings: &'static str, #[case] expected: bool) {
+        let smbios = Smbios::from(SmbiosPattern::new().with_oem_string(oem_strings));
+
+        let evidence = AZURE.evaluate(&smbios);
+
+        assert_eq!(expected, evidence.matched == [SmbiosField::OemStrings]);
+    }
+
+    #[rstest]
+    fn test_smbios_detect_product_uuid() {
+        let probe = FakeProbe::new().with_file(
+            "/sys/class/dmi/id/product_uuid",
+            "EC2E1916-9099-7CAF-FD21-012345678901\n",
+        );
+
+        let smbios = Smbios::detect(&probe);
+
+        assert_eq!(Some("EC2E1916"), smbios.get(SmbiosField::ProductUuid));
+    }
+
+    #[rstest]
+    fn test_smbios_detect_table_fallback() {
+        let probe = FakeProbe::new()
+            .with_dmi(SmbiosField::SysVendor, "Amazon EC2")
+            .with_file(
+                "/sys/firmware/dmi/tables/smbios_entry_point",
+                include_bytes!("tests/dmi/ec2_xen/smbios_entry_point").as_slice(),
+            )
+            .with_file(
+                "/sys/firmware/dmi/tables/DMI",
+                include_bytes!("tests/dmi/ec2_xen/DMI").as_slice(),
+            );
+
+        let smbios = Smbios::detect(&probe);
+
+        assert_eq!(Some("Amazon EC2"), smbios.get(SmbiosField::SysVendor));
+        assert_eq!(Some("Xen"), smbios.get(SmbiosField::BiosVendor));
+        assert_eq!(Some("ec2e1916"), smbios.get(SmbiosField::ProductUuid));
+        assert!(SmbiosPattern::new()
+            .with_oem_string("xen")
+            .evaluate(&smbios)
+            .matched
+            .contains(&SmbiosField::OemStrings));
+    }
 }
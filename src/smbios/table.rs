@@ -0,0 +1,470 @@
+//! Parser for the raw SMBIOS tables exposed under `/sys/firmware/dmi/tables`.
+//!
+//! This is used when the `/sys/class/dmi/id` files are missing, and for data that is only
+//! available in the raw tables, such as OEM strings.
+
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+use crate::{probe::SystemProbe, SmbiosField};
+
+const ENTRY_POINT: &str = "/sys/firmware/dmi/tables/smbios_entry_point";
+const TABLE: &str = "/sys/firmware/dmi/tables/DMI";
+
+const BIOS_INFORMATION: u8 = 0;
+const SYSTEM_INFORMATION: u8 = 1;
+const BASEBOARD_INFORMATION: u8 = 2;
+const SYSTEM_ENCLOSURE: u8 = 3;
+const OEM_STRINGS: u8 = 11;
+const END_OF_TABLE: u8 = 127;
+
+/// Markers kept from the OEM strings, which are otherwise discarded.
+///
+/// OEM strings can be set by whoever starts the virtual machine, and may hold secrets such as
+/// systemd credentials.
+pub(crate) const OEM_STRING_MARKERS: &[&str] = &[HYPER_V_OEM_STRING, XEN_OEM_STRING];
+
+/// Certificate string set by Hyper-V, including on Azure VMs.
+pub(crate) const HYPER_V_OEM_STRING: &str =
+    "[ms_vm_cert/sha1/9b80ca0d5dd061ec9da4e494f4c3fd1196270c22]";
+
+/// String set by the Xen `hvmloader`, including on older EC2 instance types.
+pub(crate) const XEN_OEM_STRING: &str = "xen";
+
+/// SMBIOS version, as `(major, minor)`.
+type Version = (u8, u8);
+
+/// Version assumed when the entry point can't be read.
+const DEFAULT_VERSION: Version = (3, 0);
+
+/// Relevant part of a SMBIOS entry point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct EntryPoint {
+    version: Version,
+    /// Length of the structure table, or its maximum length for 64-bit entry points.
+    table_len: usize,
+}
+
+/// Parse a 32-bit (`_SM_`), legacy (`_DMI_`) or 64-bit (`_SM3_`) entry point.
+///
+/// Returns `None` if the entry point is truncated or its checksum is invalid.
+fn parse_entry_point(bytes: &[u8]) -> Option<EntryPoint> {
+    if bytes.starts_with(b"_SM3_") {
+        let len = usize::from(*bytes.get(0x06)?);
+        checksum(bytes.get(..len)?)?;
+        Some(EntryPoint {
+            version: (*bytes.get(0x07)?, *bytes.get(0x08)?),
+            table_len: read_u32(bytes, 0x0C)? as usize,
+        })
+    } else if bytes.starts_with(b"_SM_") {
+        let len = usize::from(*bytes.get(0x05)?);
+        checksum(bytes.get(..len)?)?;
+        checksum(bytes.get(0x10..0x1F)?)?;
+        Some(EntryPoint {
+            version: (*bytes.get(0x06)?, *bytes.get(0x07)?),
+            table_len: usize::from(read_u16(bytes, 0x16)?),
+        })
+    } else if bytes.starts_with(b"_DMI_") {
+        checksum(bytes.get(..0x0F)?)?;
+        let bcd_revision = *bytes.get(0x0E)?;
+        Some(EntryPoint {
+            version: (bcd_revision >> 4, bcd_revision & 0x0F),
+            table_len: usize::from(read_u16(bytes, 0x06)?),
+        })
+    } else {
+        None
+    }
+}
+
+fn checksum(bytes: &[u8]) -> Option<()> {
+    let sum = bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    (sum == 0).then_some(())
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// Single structure from the SMBIOS table.
+#[derive(Debug)]
+struct Structure<'a> {
+    kind: u8,
+    /// Formatted area, including the 4-byte header.
+    formatted: &'a [u8],
+    strings: Vec<&'a [u8]>,
+}
+
+impl Structure<'_> {
+    fn byte(&self, offset: usize) -> Option<u8> {
+        self.formatted.get(offset).copied()
+    }
+
+    /// String referenced by the byte at `offset`.
+    fn string(&self, offset: usize) -> Option<String> {
+        let index = usize::from(self.byte(offset)?);
+        self.string_at(index.checked_sub(1)?)
+    }
+
+    fn string_at(&self, index: usize) -> Option<String> {
+        let string = String::from_utf8_lossy(self.strings.get(index)?);
+        let string = string.trim();
+        if string.is_empty() {
+            None
+        } else {
+            Some(string.to_string())
+        }
+    }
+}
+
+/// Iterate over the structures of a SMBIOS table, until the end-of-table structure.
+///
+/// Iteration stops at the first malformed structure.
+fn structures(mut table: &[u8]) -> impl Iterator<Item = Structure<'_>> {
+    std::iter::from_fn(move || {
+        let kind = *table.first()?;
+        let len = usize::from(*table.get(1)?);
+        if len < 4 || kind == END_OF_TABLE {
+            return None;
+        }
+        let formatted = table.get(..len)?;
+
+        // The string-set ends with two null bytes, including the terminator of the last string.
+        let rest = table.get(len..)?;
+        let end = rest.windows(2).position(|window| window == [0, 0])?;
+        let strings = if end == 0 {
+            Vec::new()
+        } else {
+            rest[..end].split(|byte| *byte == 0).collect()
+        };
+
+        table = &rest[end + 2..];
+        Some(Structure {
+            kind,
+            formatted,
+            strings,
+        })
+    })
+}
+
+/// Format the UUID of a system information structure, as in `/sys/class/dmi/id/product_uuid`.
+///
+/// Since SMBIOS 2.6, the first three fields are stored in little-endian.
+fn uuid(structure: &Structure<'_>, version: Version) -> Option<String> {
+    let bytes: [u8; 16] = structure.formatted.get(0x08..0x18)?.try_into().ok()?;
+    // All zeros means that the UUID is not present, and all ones that it is not set.
+    if bytes.iter().all(|byte| *byte == 0x00) || bytes.iter().all(|byte| *byte == 0xFF) {
+        return None;
+    }
+
+    let order = if version >= (2, 6) {
+        [3, 2, 1, 0, 5, 4, 7, 6, 8, 9, 10, 11, 12, 13, 14, 15]
+    } else {
+        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]
+    };
+    let mut uuid = String::with_capacity(36);
+    for (i, index) in order.into_iter().enumerate() {
+        if matches!(i, 4 | 6 | 8 | 10) {
+            uuid.push('-');
+        }
+        let _ = write!(uuid, "{:02x}", bytes[index]);
+    }
+    Some(uuid)
+}
+
+/// Extract the SMBIOS fields from a structure table.
+fn parse_table(table: &[u8], version: Version) -> BTreeMap<SmbiosField, String> {
+    let mut fields = BTreeMap::new();
+    let mut oem_strings: Vec<&str> = Vec::new();
+
+    for structure in structures(table) {
+        let values = match structure.kind {
+            BIOS_INFORMATION => vec![(SmbiosField::BiosVendor, structure.string(0x04))],
+            SYSTEM_INFORMATION => vec![
+                (SmbiosField::SysVendor, structure.string(0x04)),
+                (SmbiosField::ProductName, structure.string(0x05)),
+                (SmbiosField::ProductVersion, structure.string(0x06)),
+                (SmbiosField::ProductUuid, uuid(&structure, version)),
+                (SmbiosField::ProductFamily, structure.string(0x1A)),
+            ],
+            BASEBOARD_INFORMATION => vec![
+                (SmbiosField::BoardVendor, structure.string(0x04)),
+                (SmbiosField::BoardName, structure.string(0x05)),
+            ],
+            SYSTEM_ENCLOSURE => vec![
+                (SmbiosField::ChassisVendor, structure.string(0x04)),
+                (SmbiosField::ChassisAssetTag, structure.string(0x08)),
+            ],
+            OEM_STRINGS => {
+                let count = usize::from(structure.byte(0x04).unwrap_or(0));
+                for value in (0..count).filter_map(|index| structure.string_at(index)) {
+                    let value = value.to_lowercase();
+                    oem_strings.extend(
+                        OEM_STRING_MARKERS
+                            .iter()
+                            .copied()
+                            .filter(|marker| value.contains(marker)),
+                    );
+                }
+                continue;
+            }
+            _ => continue,
+        };
+
+        // The first value found for each field is used.
+        for (field, value) in values {
+            if let Some(value) = value {
+                fields.entry(field).or_insert(value);
+            }
+        }
+    }
+
+    oem_strings.sort_unstable();
+    oem_strings.dedup();
+    if !oem_strings.is_empty() {
+        fields.insert(SmbiosField::OemStrings, oem_strings.join("\n"));
+    }
+
+    fields
+}
+
+/// Read the SMBIOS fields from the raw tables.
+///
+/// The entry point is used for the SMBIOS version and the table length when available.
+pub(crate) fn read(probe: &(impl SystemProbe + ?Sized)) -> BTreeMap<SmbiosField, String> {
+    let Some(table) = probe.read_file(Path::new(TABLE)) else {
+        return BTreeMap::new();
+    };
+
+    let entry_point = probe
+        .read_file(Path::new(ENTRY_POINT))
+        .and_then(|bytes| parse_entry_point(&bytes));
+    let (version, table) = match entry_point {
+        Some(entry_point) => (
+            entry_point.version,
+            &table[..entry_point.table_len.min(table.len())],
+        ),
+        None => (DEFAULT_VERSION, &table[..]),
+    };
+
+    parse_table(table, version)
+}
+
+#[cfg(test)]
+mod tests {
+    //! The `dell_precision` fixture under `src/tests/dmi` is captured from real hardware, see its
+    //! `README.md`. The other fixtures are synthetic tables, built by hand with the structure
+    //! layouts of each entry point and SMBIOS version. Their values are modelled on those of each
+    //! platform, but they are not captured from real instances.
+
+    use rstest::rstest;
+
+    use super::*;
+    use crate::FakeProbe;
+
+    const EC2_XEN: (&[u8], &[u8]) = (
+        include_bytes!("../tests/dmi/ec2_xen/smbios_entry_point"),
+        include_bytes!("../tests/dmi/ec2_xen/DMI"),
+    );
+    const OPENSTACK_KVM: (&[u8], &[u8]) = (
+        include_bytes!("../tests/dmi/openstack_kvm/smbios_entry_point"),
+        include_bytes!("../tests/dmi/openstack_kvm/DMI"),
+    );
+    const AZURE: (&[u8], &[u8]) = (
+        include_bytes!("../tests/dmi/azure/smbios_entry_point"),
+        include_bytes!("../tests/dmi/azure/DMI"),
+    );
+    const DELL_PRECISION: (&[u8], &[u8]) = (
+        include_bytes!("../tests/dmi/dell_precision/smbios_entry_point"),
+        include_bytes!("../tests/dmi/dell_precision/DMI"),
+    );
+
+    fn probe((entry_point, table): (&[u8], &[u8])) -> FakeProbe {
+        FakeProbe::new()
+            .with_file(ENTRY_POINT, entry_point)
+            .with_file(TABLE, table)
+    }
+
+    #[rstest]
+    #[case::ec2_xen(EC2_XEN, (2, 7), EC2_XEN.1.len())]
+    #[case::openstack_kvm(OPENSTACK_KVM, (3, 0), OPENSTACK_KVM.1.len())]
+    #[case::azure(AZURE, (3, 1), AZURE.1.len())]
+    #[case::dell_precision(DELL_PRECISION, (3, 0), DELL_PRECISION.1.len())]
+    fn test_parse_entry_point(
+        #[case] fixture: (&[u8], &[u8]),
+        #[case] version: Version,
+        #[case] table_len: usize,
+    ) {
+        assert_eq!(
+            Some(EntryPoint { version, table_len }),
+            parse_entry_point(fixture.0)
+        );
+    }
+
+    #[rstest]
+    #[case::empty(&[])]
+    #[case::unknown_anchor(b"_XX_\x00\x00\x00\x00")]
+    #[case::truncated(&EC2_XEN.0[..0x10])]
+    fn test_parse_entry_point_invalid(#[case] bytes: &[u8]) {
+        assert_eq!(None, parse_entry_point(bytes));
+    }
+
+    #[rstest]
+    fn test_parse_entry_point_checksum() {
+        let mut bytes = AZURE.0.to_vec();
+        bytes[0x0C] ^= 0x01;
+
+        assert_eq!(None, parse_entry_point(&bytes));
+    }
+
+    #[rstest]
+    fn test_read_ec2_xen() {
+        let fields = read(&probe(EC2_XEN));
+
+        assert_eq!(
+            BTreeMap::from([
+                (SmbiosField::BiosVendor, "Xen".to_string()),
+                (SmbiosField::SysVendor, "Xen".to_string()),
+                (SmbiosField::ProductName, "HVM domU".to_string()),
+                (SmbiosField::ProductVersion, "4.11.amazon".to_string()),
+                (
+                    SmbiosField::ProductUuid,
+                    "ec2e1916-9099-7caf-fd21-012345678901".to_string()
+                ),
+                (SmbiosField::ChassisVendor, "Xen".to_string()),
+                (SmbiosField::OemStrings, "xen".to_string()),
+            ]),
+            fields
+        );
+    }
+
+    #[rstest]
+    fn test_read_openstack_kvm() {
+        let fields = read(&probe(OPENSTACK_KVM));
+
+        assert_eq!(
+            BTreeMap::from([
+                (SmbiosField::BiosVendor, "SeaBIOS".to_string()),
+                (SmbiosField::SysVendor, "OpenStack Foundation".to_string()),
+                (SmbiosField::ProductName, "OpenStack Nova".to_string()),
+                (SmbiosField::ProductVersion, "27.1.0".to_string()),
+                (
+                    SmbiosField::ProductUuid,
+                    "3f2a7c4e-5b1d-4e8a-9c3f-7d6e5b4a3c2b".to_string()
+                ),
+                (SmbiosField::ProductFamily, "Virtual Machine".to_string()),
+                (SmbiosField::ChassisVendor, "QEMU".to_string()),
+            ]),
+            fields
+        );
+    }
+
+    #[rstest]
+    fn test_read_azure() {
+        let fields = read(&probe(AZURE));
+
+        assert_eq!(
+            BTreeMap::from([
+                (SmbiosField::BiosVendor, "Microsoft Corporation".to_string()),
+                (SmbiosField::SysVendor, "Microsoft Corporation".to_string()),
+                (SmbiosField::ProductName, "Virtual Machine".to_string()),
+                (
+                    SmbiosField::ProductVersion,
+                    "Hyper-V UEFI Release v4.1".to_string()
+                ),
+                (
+                    SmbiosField::ProductUuid,
+                    "7b1e4c5a-2d3f-4a6b-8c9d-0e1f2a3b4c5d".to_string()
+                ),
+                (SmbiosField::ProductFamily, "Virtual Machine".to_string()),
+                (
+                    SmbiosField::BoardVendor,
+                    "Microsoft Corporation".to_string()
+                ),
+                (SmbiosField::BoardName, "Virtual Machine".to_string()),
+                (
+                    SmbiosField::ChassisVendor,
+                    "Microsoft Corporation".to_string()
+                ),
+                (
+                    SmbiosField::ChassisAssetTag,
+                    "7783-7084-3265-9085-8269-3286-77".to_string()
+                ),
+                (SmbiosField::OemStrings, HYPER_V_OEM_STRING.to_string()),
+            ]),
+            fields
+        );
+    }
+
+    #[rstest]
+    fn test_read_dell_precision() {
+        let fields = read(&probe(DELL_PRECISION));
+
+        // None of the OEM strings are known markers, so they are all discarded.
+        assert_eq!(
+            BTreeMap::from([
+                (SmbiosField::BiosVendor, "Dell Inc.".to_string()),
+                (SmbiosField::SysVendor, "Dell Inc.".to_string()),
+                (SmbiosField::ProductName, "Precision Tower 3620".to_string()),
+                (
+                    SmbiosField::ProductUuid,
+                    "4c4c4544-004b-4e10-805a-b7c04f424832".to_string()
+                ),
+                (SmbiosField::ProductFamily, "Precision".to_string()),
+                (SmbiosField::BoardVendor, "Dell Inc.".to_string()),
+                (SmbiosField::BoardName, "09WH54".to_string()),
+                (SmbiosField::ChassisVendor, "Dell Inc.".to_string()),
+            ]),
+            fields
+        );
+    }
+
+    #[rstest]
+    fn test_read_without_entry_point() {
+        let probe = FakeProbe::new().with_file(TABLE, EC2_XEN.1);
+
+        let fields = read(&probe);
+
+        // SMBIOS 3.0 is assumed, which matches the UUID byte order of the fixture.
+        assert_eq!(
+            Some("ec2e1916-9099-7caf-fd21-012345678901"),
+            fields.get(&SmbiosField::ProductUuid).map(String::as_str)
+        );
+    }
+
+    #[rstest]
+    fn test_read_legacy_uuid() {
+        let fields = parse_table(EC2_XEN.1, (2, 5));
+
+        assert_eq!(
+            Some("16192eec-9990-af7c-fd21-012345678901"),
+            fields.get(&SmbiosField::ProductUuid).map(String::as_str)
+        );
+    }
+
+    #[rstest]
+    fn test_read_truncated() {
+        let full = parse_table(EC2_XEN.1, (2, 7));
+        // End of the first structure, BIOS information: its formatted area, then "Xen",
+        // "4.11.amazon" and "08/24/2006", each null-terminated, and the final null byte.
+        let first = 0x18 + 4 + 12 + 11 + 1;
+
+        for len in 0..EC2_XEN.1.len() {
+            let fields = parse_table(&EC2_XEN.1[..len], (2, 7));
+
+            // Truncated structures are skipped, and the complete ones before them are kept.
+            assert_eq!(len >= first, fields.contains_key(&SmbiosField::BiosVendor));
+            for (field, value) in &fields {
+                assert_eq!(Some(value), full.get(field), "{field:?} at {len}");
+            }
+        }
+
+        assert_eq!(BTreeMap::new(), read(&FakeProbe::new()));
+    }
+}
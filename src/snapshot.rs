@@ -0,0 +1,161 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    exclusion::Exclusion, probe::SystemProbe, smbios::Smbios, sources, Detector,
+    EnvironmentDetector, Signal, SmbiosField,
+};
+
+/// Inputs read from the system during detection.
+///
+/// A snapshot holds everything the detectors are scored against, so that running the detection
+/// against a captured snapshot with
+/// [`EnvironmentDetector::detect_snapshot`](crate::EnvironmentDetector::detect_snapshot) gives
+/// the exact same result as on the machine it was captured on.
+///
+/// This only ever contains the names of environment variables that are set, never their values.
+/// Other detection sources are captured as the [`Signal`]s they produced.
+///
+/// # Examples
+///
+/// ```
+/// use apollo_environment_detector::{
+///     DetectionSnapshot, EnvironmentDetector, MAX_INDIVIDUAL_WEIGHTING,
+/// };
+///
+/// let snapshot = DetectionSnapshot::capture();
+///
+/// let report = EnvironmentDetector::new().detect_snapshot(&snapshot, MAX_INDIVIDUAL_WEIGHTING);
+/// println!("{:?}", report.best());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DetectionSnapshot {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) env_vars: BTreeSet<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) smbios: Smbios,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) signals: BTreeSet<Signal>,
+}
+
+impl DetectionSnapshot {
+    /// Capture a snapshot of the current host for the built-in detectors.
+    pub fn capture() -> Self {
+        EnvironmentDetector::new().snapshot()
+    }
+
+    pub(crate) fn new<'a>(smbios: Smbios, env_vars: impl IntoIterator<Item = &'a str>) -> Self {
+        Self {
+            env_vars: env_vars.into_iter().map(ToString::to_string).collect(),
+            smbios,
+            signals: BTreeSet::new(),
+        }
+    }
+
+    pub(crate) fn with_signals(mut self, signals: impl IntoIterator<Item = Signal>) -> Self {
+        self.signals.extend(signals);
+        self
+    }
+
+    pub(crate) fn read(detectors: &[Detector], probe: &(impl SystemProbe + ?Sized)) -> Self {
+        // Read current environment variables
+        let env_vars = detectors
+            .iter()
+            .flat_map(|detector| {
+                let exclusions = detector.exclusions.iter().filter_map(Exclusion::env_var);
+                detector.env_vars.iter().copied().chain(exclusions)
+            })
+            .filter(|var| probe.has_env(var));
+
+        // Read SMBIOS data
+        let smbios = Smbios::detect(probe);
+
+        // Read signals from other sources
+        let signals = sources::read(probe);
+
+        Self::new(smbios, env_vars).with_signals(signals)
+    }
+
+    /// Names of the environment variables that were set, out of those expected or excluded by the
+    /// detectors.
+    pub fn env_vars(&self) -> impl Iterator<Item = &str> {
+        self.env_vars.iter().map(String::as_str)
+    }
+
+    /// Value of a SMBIOS field, if any.
+    pub fn smbios(&self, field: SmbiosField) -> Option<&str> {
+        self.smbios.get(field)
+    }
+
+    /// Signals observed by the other detection sources.
+    pub fn signals(&self) -> impl Iterator<Item = Signal> + '_ {
+        self.signals.iter().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::{
+        env_vars, ComputeEnvironment, EnvironmentDetector, FakeProbe, SmbiosField,
+        MAX_TOTAL_WEIGHTING,
+    };
+
+    #[rstest]
+    fn test_snapshot() {
+        let probe = env_vars::KUBERNETES.iter().fold(
+            FakeProbe::new()
+                .with_env("UNRELATED_ENV")
+                .with_dmi(SmbiosField::BiosVendor, "Google")
+                .with_dmi(SmbiosField::SysVendor, "Google"),
+            |probe, env_var| probe.with_env(*env_var),
+        );
+        let detector = EnvironmentDetector::new().with_probe(probe);
+
+        let snapshot = detector.snapshot();
+
+        let mut expected = env_vars::KUBERNETES.to_vec();
+        expected.sort();
+        assert_eq!(expected, snapshot.env_vars().collect::<Vec<_>>());
+        assert_eq!(Some("Google"), snapshot.smbios(SmbiosField::BiosVendor));
+        assert_eq!(None, snapshot.smbios(SmbiosField::ProductName));
+        assert_eq!(Some("Google"), snapshot.smbios(SmbiosField::SysVendor));
+
+        // Replaying the snapshot without the original probe gives the same report.
+        let report = EnvironmentDetector::new().detect_snapshot(&snapshot, u16::MIN);
+        assert_eq!(detector.detect_report(u16::MIN), report);
+        assert_eq!(
+            vec![ComputeEnvironment::GcpKubernetes],
+            EnvironmentDetector::new()
+                .detect_snapshot(&snapshot, MAX_TOTAL_WEIGHTING)
+                .environments()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[rstest]
+    fn test_snapshot_roundtrip() {
+        use super::DetectionSnapshot;
+
+        let snapshot = EnvironmentDetector::new()
+            .with_probe(
+                FakeProbe::new()
+                    .with_env("KUBERNETES_SERVICE_HOST")
+                    .with_dmi(SmbiosField::SysVendor, "QEMU"),
+            )
+            .snapshot();
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(
+            r#"{"env_vars":["KUBERNETES_SERVICE_HOST"],"smbios":{"sys_vendor":"QEMU"},"signals":[]}"#,
+            json
+        );
+
+        let deserialized: DetectionSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(snapshot, deserialized);
+
+        let empty: DetectionSnapshot = serde_json::from_str("{}").unwrap();
+        assert_eq!(DetectionSnapshot::default(), empty);
+    }
+}
@@ -0,0 +1,191 @@
+//! CPU family and hypervisor detection on ARM hosts, from the device tree and the CPU
+//! identification registers.
+//!
+//! ARM instances often expose little or no SMBIOS data. Their CPU cores are shared by several
+//! cloud providers and other vendors, so the CPU family is only a hint towards a cloud provider.
+
+use std::{collections::BTreeSet, path::Path};
+
+use crate::{probe::SystemProbe, CpuFamily, Hypervisor, Signal};
+
+const DEVICE_TREE_COMPATIBLE: &str = "/proc/device-tree/compatible";
+const DEVICE_TREE_MODEL: &str = "/sys/firmware/devicetree/base/model";
+const MIDR_EL1: &str = "/sys/devices/system/cpu/cpu0/regs/identification/midr_el1";
+const CPUINFO: &str = "/proc/cpuinfo";
+
+// Device tree `compatible` and `model` values. QEMU and Firecracker name their virtual machines
+// `linux,dummy-virt`, and are mostly used with KVM.
+const DEVICE_TREE: &[(&str, Signal)] = &[("linux,dummy-virt", Signal::Hypervisor(Hypervisor::Kvm))];
+
+// Implementer codes of `MIDR_EL1`.
+const ARM: u8 = 0x41;
+const AMPERE: u8 = 0xc0;
+
+/// Detect the CPU family and hypervisor from the device tree and the CPU of the current host.
+pub(crate) fn detect(probe: &(impl SystemProbe + ?Sized)) -> BTreeSet<Signal> {
+    let mut signals = BTreeSet::new();
+
+    if let Some(compatible) = probe.read_file(Path::new(DEVICE_TREE_COMPATIBLE)) {
+        signals.extend(parse_device_tree(&compatible));
+    }
+    if let Some(model) = probe.read_file(Path::new(DEVICE_TREE_MODEL)) {
+        signals.extend(parse_device_tree(&model));
+    }
+
+    let cpu = probe
+        .read_file(Path::new(MIDR_EL1))
+        .and_then(|midr| parse_midr(&String::from_utf8_lossy(&midr)))
+        .or_else(|| {
+            let cpuinfo = probe.read_file(Path::new(CPUINFO))?;
+            parse_cpuinfo(&String::from_utf8_lossy(&cpuinfo))
+        });
+    if let Some(cpu_family) = cpu.and_then(|(implementer, part)| cpu_family(implementer, part)) {
+        signals.insert(Signal::Cpu(cpu_family));
+    }
+
+    signals
+}
+
+/// Parse a device tree property, made of NUL-terminated strings.
+fn parse_device_tree(content: &[u8]) -> BTreeSet<Signal> {
+    content
+        .split(|byte| *byte == 0)
+        .filter_map(|value| {
+            let value = String::from_utf8_lossy(value).trim().to_lowercase();
+            DEVICE_TREE
+                .iter()
+                .find_map(|(expected, signal)| (*expected == value).then_some(*signal))
+        })
+        .collect()
+}
+
+/// Parse the implementer and part number of the `MIDR_EL1` register, such as
+/// `0x00000000413fd0c1`.
+fn parse_midr(content: &str) -> Option<(u8, u16)> {
+    let content = content.trim();
+    let midr = u64::from_str_radix(content.strip_prefix("0x").unwrap_or(content), 16).ok()?;
+
+    Some(((midr >> 24) as u8, ((midr >> 4) & 0xfff) as u16))
+}
+
+/// Parse the implementer and part number of the first CPU of `/proc/cpuinfo`.
+fn parse_cpuinfo(content: &str) -> Option<(u8, u16)> {
+    let field = |name| {
+        content.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            if key.trim() != name {
+                return None;
+            }
+            let value = value.trim();
+            u16::from_str_radix(value.strip_prefix("0x").unwrap_or(value), 16).ok()
+        })
+    };
+
+    Some((
+        field("CPU implementer")?.try_into().ok()?,
+        field("CPU part")?,
+    ))
+}
+
+/// CPU family of an implementer and part number.
+fn cpu_family(implementer: u8, part: u16) -> Option<CpuFamily> {
+    match (implementer, part) {
+        (ARM, 0xd08) => Some(CpuFamily::CortexA72),
+        (ARM, 0xd0c) => Some(CpuFamily::NeoverseN1),
+        (ARM, 0xd40) => Some(CpuFamily::NeoverseV1),
+        (ARM, 0xd49) => Some(CpuFamily::NeoverseN2),
+        (ARM, 0xd4f) => Some(CpuFamily::NeoverseV2),
+        // AmpereOne, AmpereOne A and AmpereOne B. Ampere Altra CPUs are Neoverse N1 cores.
+        (AMPERE, 0xac3..=0xac5) => Some(CpuFamily::AmpereOne),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::FakeProbe;
+
+    const GRAVITON2_CPUINFO: &str = "\
+processor\t: 0
+BogoMIPS\t: 243.75
+Features\t: fp asimd evtstrm aes pmull sha1 sha2 crc32 atomics fphp asimdhp cpuid asimdrdm lrcpc dcpop asimddp ssbs
+CPU implementer\t: 0x41
+CPU architecture: 8
+CPU variant\t: 0x3
+CPU part\t: 0xd0c
+CPU revision\t: 1
+
+processor\t: 1
+CPU implementer\t: 0x41
+CPU part\t: 0xd0c
+";
+
+    #[rstest]
+    #[case::qemu(b"linux,dummy-virt\0", &[Signal::Hypervisor(Hypervisor::Kvm)])]
+    #[case::board(b"raspberrypi,4-model-b\0brcm,bcm2711\0", &[])]
+    #[case::empty(b"", &[])]
+    fn test_parse_device_tree(#[case] content: &[u8], #[case] expected: &[Signal]) {
+        assert_eq!(
+            expected,
+            parse_device_tree(content).into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[rstest]
+    #[case::graviton3("0x00000000411fd401\n", Some((ARM, 0xd40)))]
+    #[case::ampere_one("0x00000000c00fac30\n", Some((AMPERE, 0xac3)))]
+    #[case::invalid("unknown\n", None)]
+    fn test_parse_midr(#[case] content: &str, #[case] expected: Option<(u8, u16)>) {
+        assert_eq!(expected, parse_midr(content));
+    }
+
+    #[rstest]
+    #[case::graviton2(GRAVITON2_CPUINFO, Some((ARM, 0xd0c)))]
+    #[case::x86_64("processor\t: 0\nvendor_id\t: GenuineIntel\ncpu family\t: 6\n", None)]
+    #[case::missing_part("CPU implementer\t: 0x41\n", None)]
+    fn test_parse_cpuinfo(#[case] content: &str, #[case] expected: Option<(u8, u16)>) {
+        assert_eq!(expected, parse_cpuinfo(content));
+    }
+
+    #[rstest]
+    #[case::cortex_a72(ARM, 0xd08, Some(CpuFamily::CortexA72))]
+    #[case::neoverse_n1(ARM, 0xd0c, Some(CpuFamily::NeoverseN1))]
+    #[case::neoverse_v1(ARM, 0xd40, Some(CpuFamily::NeoverseV1))]
+    #[case::neoverse_n2(ARM, 0xd49, Some(CpuFamily::NeoverseN2))]
+    #[case::neoverse_v2(ARM, 0xd4f, Some(CpuFamily::NeoverseV2))]
+    #[case::ampere_one(AMPERE, 0xac3, Some(CpuFamily::AmpereOne))]
+    #[case::ampere_one_a(AMPERE, 0xac4, Some(CpuFamily::AmpereOne))]
+    #[case::ampere_one_b(AMPERE, 0xac5, Some(CpuFamily::AmpereOne))]
+    #[case::ampere_unknown(AMPERE, 0x000, None)]
+    #[case::cortex_a53(ARM, 0xd03, None)]
+    #[case::apple(0x61, 0x022, None)]
+    fn test_cpu_family(
+        #[case] implementer: u8,
+        #[case] part: u16,
+        #[case] expected: Option<CpuFamily>,
+    ) {
+        assert_eq!(expected, cpu_family(implementer, part));
+    }
+
+    #[rstest]
+    #[case::none(FakeProbe::new(), &[])]
+    #[case::midr_over_cpuinfo(
+        FakeProbe::new()
+            .with_file(MIDR_EL1, "0x00000000410fd4f0\n")
+            .with_file(CPUINFO, GRAVITON2_CPUINFO),
+        &[Signal::Cpu(CpuFamily::NeoverseV2)]
+    )]
+    #[case::qemu_virt(
+        FakeProbe::new()
+            .with_file(DEVICE_TREE_COMPATIBLE, "linux,dummy-virt\0")
+            .with_file(DEVICE_TREE_MODEL, "linux,dummy-virt\0")
+            .with_file(CPUINFO, GRAVITON2_CPUINFO),
+        &[Signal::Hypervisor(Hypervisor::Kvm), Signal::Cpu(CpuFamily::NeoverseN1)]
+    )]
+    fn test_detect(#[case] probe: FakeProbe, #[case] expected: &[Signal]) {
+        assert_eq!(expected, detect(&probe).into_iter().collect::<Vec<_>>());
+    }
+}
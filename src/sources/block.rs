@@ -0,0 +1,147 @@
+//! Cloud provider and hypervisor detection from the model and vendor of block devices.
+
+use std::{collections::BTreeSet, path::Path};
+
+use crate::{probe::SystemProbe, CloudProvider, Hypervisor, Signal};
+
+const BLOCK_DEVICES: &str = "/sys/block";
+
+// Prefixes of the models of the disks attached by cloud providers.
+const MODELS: &[(&str, Signal)] = &[
+    // EBS volumes.
+    (
+        "amazon elastic block store",
+        Signal::CloudProvider(CloudProvider::Aws),
+    ),
+    // Instance store volumes.
+    (
+        "amazon ec2 nvme instance storage",
+        Signal::CloudProvider(CloudProvider::Aws),
+    ),
+    // Persistent disks and local SSDs, either as SCSI or NVMe devices.
+    (
+        "persistentdisk",
+        Signal::CloudProvider(CloudProvider::GoogleCloud),
+    ),
+    (
+        "ephemeraldisk",
+        Signal::CloudProvider(CloudProvider::GoogleCloud),
+    ),
+    (
+        "nvme_card",
+        Signal::CloudProvider(CloudProvider::GoogleCloud),
+    ),
+];
+
+// Vendors and models of the disks attached by hypervisors. Azure disks can't be told apart from
+// any other Hyper-V disk.
+const VENDOR_MODELS: &[(&str, &str, Signal)] = &[(
+    "msft",
+    "virtual disk",
+    Signal::Hypervisor(Hypervisor::HyperV),
+)];
+
+/// Detect the cloud provider and hypervisor from the block devices of the current host.
+pub(crate) fn detect(probe: &(impl SystemProbe + ?Sized)) -> BTreeSet<Signal> {
+    probe
+        .read_dir(Path::new(BLOCK_DEVICES))
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|device| {
+            let read = |name| {
+                let content = probe.read_file(&device.join("device").join(name))?;
+                Some(String::from_utf8_lossy(&content).into_owned())
+            };
+            parse_device(read("vendor").as_deref(), read("model")?.as_str())
+        })
+        .collect()
+}
+
+/// Parse the vendor and model of a block device.
+///
+/// The vendor is only available for SCSI devices, and is padded with spaces as the model.
+fn parse_device(vendor: Option<&str>, model: &str) -> Option<Signal> {
+    let vendor = vendor.map(|vendor| vendor.trim().to_lowercase());
+    let model = model.trim().to_lowercase();
+
+    MODELS
+        .iter()
+        .find_map(|(prefix, signal)| model.starts_with(prefix).then_some(*signal))
+        .or_else(|| {
+            VENDOR_MODELS
+                .iter()
+                .find_map(|(expected_vendor, prefix, signal)| {
+                    (vendor.as_deref() == Some(*expected_vendor) && model.starts_with(prefix))
+                        .then_some(*signal)
+                })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::FakeProbe;
+
+    #[rstest]
+    #[case::ebs(
+        None,
+        "Amazon Elastic Block Store              \n",
+        Some(Signal::CloudProvider(CloudProvider::Aws))
+    )]
+    #[case::instance_store(
+        None,
+        "Amazon EC2 NVMe Instance Storage        \n",
+        Some(Signal::CloudProvider(CloudProvider::Aws))
+    )]
+    #[case::gcp_scsi(
+        Some("Google  \n"),
+        "PersistentDisk  \n",
+        Some(Signal::CloudProvider(CloudProvider::GoogleCloud))
+    )]
+    #[case::gcp_local_ssd(
+        Some("Google  \n"),
+        "EphemeralDisk   \n",
+        Some(Signal::CloudProvider(CloudProvider::GoogleCloud))
+    )]
+    #[case::gcp_nvme(
+        None,
+        "nvme_card-pd\n",
+        Some(Signal::CloudProvider(CloudProvider::GoogleCloud))
+    )]
+    #[case::hyper_v(
+        Some("Msft    \n"),
+        "Virtual Disk    \n",
+        Some(Signal::Hypervisor(Hypervisor::HyperV))
+    )]
+    #[case::other_vendor(Some("ATA     \n"), "Virtual Disk    \n", None)]
+    #[case::qemu(Some("QEMU    \n"), "QEMU HARDDISK   \n", None)]
+    #[case::physical(None, "Samsung SSD 980 PRO 1TB\n", None)]
+    fn test_parse_device(
+        #[case] vendor: Option<&str>,
+        #[case] model: &str,
+        #[case] expected: Option<Signal>,
+    ) {
+        assert_eq!(expected, parse_device(vendor, model));
+    }
+
+    #[rstest]
+    #[case::none(FakeProbe::new(), &[])]
+    #[case::aws(
+        FakeProbe::new()
+            .with_file("/sys/block/loop0/ro", "0\n")
+            .with_file("/sys/block/nvme0n1/device/model", "Amazon Elastic Block Store              \n")
+            .with_file("/sys/block/nvme1n1/device/model", "Amazon EC2 NVMe Instance Storage        \n"),
+        &[Signal::CloudProvider(CloudProvider::Aws)]
+    )]
+    #[case::azure(
+        FakeProbe::new()
+            .with_file("/sys/block/sda/device/vendor", "Msft    \n")
+            .with_file("/sys/block/sda/device/model", "Virtual Disk    \n"),
+        &[Signal::Hypervisor(Hypervisor::HyperV)]
+    )]
+    fn test_detect(#[case] probe: FakeProbe, #[case] expected: &[Signal]) {
+        assert_eq!(expected, detect(&probe).into_iter().collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,218 @@
+//! Cloud provider detection from the cloud-init data.
+//!
+//! cloud-init names the datasource it used to configure the instance, which corresponds to the
+//! cloud provider. Only world-readable files are used, never `instance-data-sensitive.json`.
+//!
+//! The region and instance type are not read: they don't tell compute environments apart, and
+//! signals only name the kind of environment, not where or on what it runs.
+
+use super::read_to_string;
+use crate::{probe::SystemProbe, CloudProvider};
+
+const CLOUD_ID: &str = "/run/cloud-init/cloud-id";
+const INSTANCE_DATA: &str = "/run/cloud-init/instance-data.json";
+const RESULT: &str = "/var/lib/cloud/data/result.json";
+
+/// Detect the cloud provider from the cloud-init data, if any.
+///
+/// `cloud-id` is the most precise source, `instance-data.json` the fallback for older versions of
+/// cloud-init, and `result.json` the fallback when `/run` was cleared since the last boot. The
+/// latter only names the datasource, which some other clouds share with AWS.
+pub(crate) fn detect(probe: &(impl SystemProbe + ?Sized)) -> Option<CloudProvider> {
+    if let Some(cloud_id) = read_to_string(probe, CLOUD_ID) {
+        return parse_cloud_name(cloud_id.trim());
+    }
+
+    if let Some(instance_data) = read_to_string(probe, INSTANCE_DATA) {
+        // EC2-compatible clouds have the `ec2` platform, but an `unknown` cloud name.
+        return v1_string(&instance_data, "cloud_name").and_then(parse_cloud_name);
+    }
+
+    read_to_string(probe, RESULT)
+        .as_deref()
+        .and_then(|result| v1_string(result, "datasource"))
+        .and_then(parse_datasource)
+}
+
+/// Parse a cloud name, as found in `cloud-id` or `instance-data.json`.
+///
+/// Regional partitions have their own cloud name, such as `aws-china` or `azure-gov`. Unknown
+/// clouds are named after their platform instead, such as `ec2` for EC2-compatible clouds.
+fn parse_cloud_name(name: &str) -> Option<CloudProvider> {
+    let name = name.to_lowercase();
+    let base = name.split('-').next().unwrap_or_default();
+
+    match base {
+        "aws" => Some(CloudProvider::Aws),
+        "azure" => Some(CloudProvider::Azure),
+        "gce" => Some(CloudProvider::GoogleCloud),
+        _ => None,
+    }
+}
+
+/// Parse a datasource name, such as `DataSourceEc2Local [seed=...]`.
+fn parse_datasource(datasource: &str) -> Option<CloudProvider> {
+    let name = datasource.split_whitespace().next()?.to_lowercase();
+    let name = name.strip_prefix("datasource").unwrap_or(&name);
+    let name = name
+        .strip_suffix("local")
+        .or_else(|| name.strip_suffix("net"))
+        .unwrap_or(name);
+
+    match name {
+        "ec2" => Some(CloudProvider::Aws),
+        name => parse_cloud_name(name),
+    }
+}
+
+/// Returns the string value of a key of the top-level `v1` object of a cloud-init JSON document.
+///
+/// cloud-init writes its documents with sorted keys, so the last `v1` object is the top-level
+/// one, after the datasource metadata under `ds`. The `v1` object is flat, with only strings,
+/// lists of strings and `null` as values, so it is read up to its first closing brace outside of
+/// a string.
+///
+/// Returns `None` if the value is not a string, or if it contains escape sequences, which the
+/// cloud and datasource names never do.
+fn v1_string<'a>(content: &'a str, key: &str) -> Option<&'a str> {
+    let mut rest = content.rmatch_indices(r#""v1""#).find_map(|(index, v1)| {
+        let rest = content[index + v1.len()..].trim_start();
+        rest.strip_prefix(':')?.trim_start().strip_prefix('{')
+    })?;
+
+    loop {
+        let start = rest.find(['"', '}'])?;
+        let (name, after) = split_string(&rest[start..])?;
+        rest = after.trim_start();
+        let Some(value) = rest.strip_prefix(':') else {
+            // Strings in a list, or values of other keys.
+            continue;
+        };
+        rest = value.trim_start();
+        if name == key {
+            return split_string(rest)
+                .map(|(value, _)| value)
+                .filter(|value| !value.contains('\\'));
+        }
+    }
+}
+
+/// Split a JSON string at the start of `content` from the rest of `content`, without unescaping
+/// it.
+///
+/// Returns `None` if `content` doesn't start with a complete string.
+fn split_string(content: &str) -> Option<(&str, &str)> {
+    let content = content.strip_prefix('"')?;
+    let mut escaped = false;
+    let (end, _) = content.char_indices().find(|(_, char)| {
+        let end = !escaped && *char == '"';
+        escaped = !escaped && *char == '\\';
+        end
+    })?;
+
+    Some((&content[..end], &content[end + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::FakeProbe;
+
+    #[rstest]
+    #[case::aws("aws", Some(CloudProvider::Aws))]
+    #[case::aws_china("aws-china", Some(CloudProvider::Aws))]
+    #[case::aws_gov("aws-gov", Some(CloudProvider::Aws))]
+    #[case::ec2("ec2", None)]
+    #[case::azure("azure", Some(CloudProvider::Azure))]
+    #[case::azure_china("azure-china", Some(CloudProvider::Azure))]
+    #[case::gce("gce", Some(CloudProvider::GoogleCloud))]
+    #[case::uppercase("GCE", Some(CloudProvider::GoogleCloud))]
+    #[case::openstack("openstack", None)]
+    #[case::oracle("oracle", None)]
+    #[case::digitalocean("digitalocean", None)]
+    #[case::nocloud("nocloud", None)]
+    #[case::empty("", None)]
+    fn test_parse_cloud_name(#[case] name: &str, #[case] expected: Option<CloudProvider>) {
+        assert_eq!(expected, parse_cloud_name(name));
+    }
+
+    #[rstest]
+    #[case::ec2_local("DataSourceEc2Local", Some(CloudProvider::Aws))]
+    #[case::ec2("DataSourceEc2", Some(CloudProvider::Aws))]
+    #[case::azure("DataSourceAzure [seed=/dev/sr0]", Some(CloudProvider::Azure))]
+    #[case::gce_local("DataSourceGCELocal", Some(CloudProvider::GoogleCloud))]
+    #[case::openstack("DataSourceOpenStackLocal [net,ver=2]", None)]
+    #[case::none("DataSourceNone", None)]
+    fn test_parse_datasource(#[case] datasource: &str, #[case] expected: Option<CloudProvider>) {
+        assert_eq!(expected, parse_datasource(datasource));
+    }
+
+    // Modelled on the EC2 example of the cloud-init documentation, with made-up identifiers.
+    const EC2_INSTANCE_DATA: &str = include_str!("../tests/cloud_init/instance-data.json");
+
+    #[rstest]
+    #[case::value(r#"{"v1": {"cloud_name": "aws"}}"#, Some("aws"))]
+    #[case::whitespace("{\"v1\" : {\"cloud_name\" :\n \"gce\"}\n}", Some("gce"))]
+    #[case::other_values(
+        r#"{"a": [1, {"b": "}"}], "v1": {"c": ["d", "e"], "f": null, "cloud_name": "azure"}}"#,
+        Some("azure")
+    )]
+    #[case::brace_in_string(r#"{"v1": {"a": "}", "cloud_name": "aws"}}"#, Some("aws"))]
+    #[case::nested_v1(
+        r#"{"ds": {"v1": {"cloud_name": "aws"}}, "v1": {"cloud_name": "gce"}}"#,
+        Some("gce")
+    )]
+    #[case::v1_value(r#"{"v1": {"cloud_name": "aws"}, "variant": "v1"}"#, Some("aws"))]
+    #[case::nested_key(r#"{"ds": {"cloud_name": "aws"}, "v1": {}}"#, None)]
+    #[case::key_in_string(r#"{"v1": {"_doc": "\"cloud_name\": \"aws\""}}"#, None)]
+    #[case::escapes(r#"{"v1": {"cloud_name": "a\"b"}}"#, None)]
+    #[case::not_string(r#"{"v1": {"cloud_name": null}}"#, None)]
+    #[case::prefix_key(r#"{"v1": {"cloud_name_v2": "aws"}}"#, None)]
+    #[case::missing(r#"{"v1": {}}"#, None)]
+    #[case::top_level(r#"{"cloud_name": "aws"}"#, None)]
+    #[case::truncated(r#"{"v1": {"cloud_name": "aws"#, None)]
+    #[case::ec2(EC2_INSTANCE_DATA, Some("aws"))]
+    fn test_v1_string(#[case] content: &str, #[case] expected: Option<&str>) {
+        assert_eq!(expected, v1_string(content, "cloud_name"));
+    }
+
+    #[rstest]
+    #[case::none(FakeProbe::new(), None)]
+    #[case::cloud_id(FakeProbe::new().with_file(CLOUD_ID, "aws\n"), Some(CloudProvider::Aws))]
+    #[case::cloud_id_over_instance_data(
+        FakeProbe::new()
+            .with_file(CLOUD_ID, "openstack\n")
+            .with_file(INSTANCE_DATA, r#"{"v1": {"cloud_name": "aws"}}"#),
+        None
+    )]
+    #[case::instance_data(
+        FakeProbe::new().with_file(
+            INSTANCE_DATA,
+            r#"{"v1": {"cloud_name": "gce", "platform": "gce", "region": "us-central1"}}"#
+        ),
+        Some(CloudProvider::GoogleCloud)
+    )]
+    #[case::instance_data_unknown(
+        FakeProbe::new().with_file(
+            INSTANCE_DATA,
+            r#"{"v1": {"cloud_name": "unknown", "platform": "ec2"}}"#
+        ),
+        None
+    )]
+    #[case::instance_data_ec2(
+        FakeProbe::new().with_file(INSTANCE_DATA, EC2_INSTANCE_DATA),
+        Some(CloudProvider::Aws)
+    )]
+    #[case::result(
+        FakeProbe::new().with_file(
+            RESULT,
+            r#"{"v1": {"datasource": "DataSourceAzure [seed=/dev/sr0]", "errors": []}}"#
+        ),
+        Some(CloudProvider::Azure)
+    )]
+    fn test_detect(#[case] probe: FakeProbe, #[case] expected: Option<CloudProvider>) {
+        assert_eq!(expected, detect(&probe));
+    }
+}
@@ -0,0 +1,184 @@
+//! Container runtime detection from filesystem markers and cgroups.
+
+use std::path::Path;
+
+use super::read_to_string;
+use crate::{probe::SystemProbe, ContainerRuntime};
+
+const DOCKERENV: &str = "/.dockerenv";
+const CONTAINERENV: &str = "/run/.containerenv";
+const CGROUP: &str = "/proc/self/cgroup";
+const MOUNTINFO: &str = "/proc/self/mountinfo";
+
+// Markers found in the cgroup paths of `/proc/self/cgroup`, in order of precedence.
+const CGROUP_MARKERS: &[(&str, ContainerRuntime)] = &[
+    ("crio-", ContainerRuntime::CriO),
+    ("/crio/", ContainerRuntime::CriO),
+    ("libpod-", ContainerRuntime::Podman),
+    ("/libpod_parent/", ContainerRuntime::Podman),
+    ("cri-containerd-", ContainerRuntime::Containerd),
+    ("docker-", ContainerRuntime::Docker),
+    ("/docker/", ContainerRuntime::Docker),
+];
+
+// Markers found in the mount source and options of the root mount in `/proc/self/mountinfo`, in
+// order of precedence. The `containers/storage` of CRI-O is left out, as Podman and Buildah share
+// it.
+const MOUNTINFO_MARKERS: &[(&str, ContainerRuntime)] = &[
+    ("/var/lib/docker/", ContainerRuntime::Docker),
+    ("/run/containerd/", ContainerRuntime::Containerd),
+    ("/var/lib/containerd/", ContainerRuntime::Containerd),
+];
+
+/// Detect the container runtime managing the current process, if any.
+///
+/// cgroup paths are the most precise source, but are hidden when the container runs in its own
+/// cgroup namespace, which is the default with cgroup v2. The marker files and the mounts are
+/// used as a fallback.
+pub(crate) fn detect(probe: &(impl SystemProbe + ?Sized)) -> Option<ContainerRuntime> {
+    let cgroup = read_to_string(probe, CGROUP);
+    if let Some(container_runtime) = cgroup.as_deref().and_then(parse_cgroup) {
+        return Some(container_runtime);
+    }
+
+    if probe.exists(Path::new(DOCKERENV)) {
+        return Some(ContainerRuntime::Docker);
+    }
+    if probe.exists(Path::new(CONTAINERENV)) {
+        return Some(ContainerRuntime::Podman);
+    }
+
+    read_to_string(probe, MOUNTINFO)
+        .as_deref()
+        .and_then(parse_mountinfo)
+}
+
+/// Parse the content of `/proc/self/cgroup`.
+///
+/// Each line has the `hierarchy-ID:controller-list:cgroup-path` format.
+fn parse_cgroup(content: &str) -> Option<ContainerRuntime> {
+    let paths: Vec<_> = content
+        .lines()
+        .filter_map(|line| line.splitn(3, ':').nth(2))
+        .collect();
+
+    CGROUP_MARKERS
+        .iter()
+        .find_map(|(marker, container_runtime)| {
+            paths
+                .iter()
+                .any(|path| path.contains(marker))
+                .then_some(*container_runtime)
+        })
+}
+
+/// Parse the content of `/proc/self/mountinfo`.
+///
+/// Only the root mount is used, the fifth field of its line being `/`. Processes on the host of
+/// a container runtime see the mounts of the containers as well, under other mount points.
+fn parse_mountinfo(content: &str) -> Option<ContainerRuntime> {
+    let root = content
+        .lines()
+        .find(|line| line.split_whitespace().nth(4) == Some("/"))?;
+
+    MOUNTINFO_MARKERS
+        .iter()
+        .find_map(|(marker, container_runtime)| root.contains(marker).then_some(*container_runtime))
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::FakeProbe;
+
+    const CGROUP_V2_NAMESPACED: &str = "0::/\n";
+
+    #[rstest]
+    #[case::docker_v1(
+        "12:pids:/docker/4b1d6c5a0e7f\n11:memory:/docker/4b1d6c5a0e7f\n",
+        Some(ContainerRuntime::Docker)
+    )]
+    #[case::docker_systemd(
+        "0::/system.slice/docker-4b1d6c5a0e7f.scope\n",
+        Some(ContainerRuntime::Docker)
+    )]
+    #[case::podman(
+        "0::/machine.slice/libpod-4b1d6c5a0e7f.scope/container\n",
+        Some(ContainerRuntime::Podman)
+    )]
+    #[case::crio(
+        "0::/kubepods.slice/kubepods-burstable.slice/kubepods-burstable-pod1234.slice/crio-4b1d6c5a0e7f.scope\n",
+        Some(ContainerRuntime::CriO)
+    )]
+    #[case::containerd(
+        "0::/kubepods.slice/kubepods-besteffort.slice/kubepods-besteffort-pod1234.slice/cri-containerd-4b1d6c5a0e7f.scope\n",
+        Some(ContainerRuntime::Containerd)
+    )]
+    #[case::kubernetes_docker(
+        "11:memory:/kubepods/besteffort/pod1234/docker-4b1d6c5a0e7f.scope\n",
+        Some(ContainerRuntime::Docker)
+    )]
+    #[case::host_systemd("0::/user.slice/user-1000.slice/session-2.scope\n", None)]
+    #[case::namespaced(CGROUP_V2_NAMESPACED, None)]
+    #[case::empty("", None)]
+    fn test_parse_cgroup(#[case] content: &str, #[case] expected: Option<ContainerRuntime>) {
+        assert_eq!(expected, parse_cgroup(content));
+    }
+
+    #[rstest]
+    #[case::docker(
+        "1167 1095 0:170 / / rw,relatime master:420 - overlay overlay rw,lowerdir=/var/lib/docker/overlay2/l/ABC:/var/lib/docker/overlay2/l/DEF,upperdir=/var/lib/docker/overlay2/123/diff,workdir=/var/lib/docker/overlay2/123/work\n",
+        Some(ContainerRuntime::Docker)
+    )]
+    #[case::containerd(
+        "2193 2140 0:330 / / rw,relatime - overlay overlay rw,lowerdir=/var/lib/containerd/io.containerd.snapshotter.v1.overlayfs/snapshots/10/fs,upperdir=/var/lib/containerd/io.containerd.snapshotter.v1.overlayfs/snapshots/11/fs\n",
+        Some(ContainerRuntime::Containerd)
+    )]
+    #[case::containers_storage(
+        "2716 2650 0:356 / / rw,relatime - overlay overlay rw,lowerdir=/var/lib/containers/storage/overlay/l/ABC,upperdir=/var/lib/containers/storage/overlay/123/diff\n",
+        None
+    )]
+    #[case::host("22 1 259:2 / / rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw\n", None)]
+    #[case::container_host(
+        "22 1 259:2 / / rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw\n\
+         1480 29 0:170 / /var/lib/docker/overlay2/123/merged rw,relatime shared:704 - overlay overlay rw,lowerdir=/var/lib/docker/overlay2/l/ABC,upperdir=/var/lib/docker/overlay2/123/diff\n\
+         2205 29 0:330 / /run/containerd/io.containerd.runtime.v2.task/k8s.io/456/rootfs rw,relatime shared:901 - overlay overlay rw,lowerdir=/var/lib/containerd/io.containerd.snapshotter.v1.overlayfs/snapshots/10/fs\n",
+        None
+    )]
+    #[case::empty("", None)]
+    fn test_parse_mountinfo(#[case] content: &str, #[case] expected: Option<ContainerRuntime>) {
+        assert_eq!(expected, parse_mountinfo(content));
+    }
+
+    #[rstest]
+    #[case::none(FakeProbe::new(), None)]
+    #[case::dockerenv(FakeProbe::new().with_file(DOCKERENV, ""), Some(ContainerRuntime::Docker))]
+    #[case::containerenv(
+        FakeProbe::new().with_file(CONTAINERENV, "engine=\"podman-4.9.3\"\n"),
+        Some(ContainerRuntime::Podman)
+    )]
+    #[case::cgroup_over_marker(
+        FakeProbe::new()
+            .with_file(DOCKERENV, "")
+            .with_file(CGROUP, "0::/kubepods.slice/crio-4b1d6c5a0e7f.scope\n"),
+        Some(ContainerRuntime::CriO)
+    )]
+    #[case::marker_over_mountinfo(
+        FakeProbe::new()
+            .with_file(CGROUP, CGROUP_V2_NAMESPACED)
+            .with_file(CONTAINERENV, "")
+            .with_file(MOUNTINFO, "1 0 0:1 / / rw - overlay overlay lowerdir=/var/lib/containers/storage/overlay/l/ABC\n"),
+        Some(ContainerRuntime::Podman)
+    )]
+    #[case::mountinfo(
+        FakeProbe::new()
+            .with_file(CGROUP, CGROUP_V2_NAMESPACED)
+            .with_file(MOUNTINFO, "1 0 0:1 / / rw - overlay overlay lowerdir=/var/lib/docker/overlay2/l/ABC\n"),
+        Some(ContainerRuntime::Docker)
+    )]
+    fn test_detect(#[case] probe: FakeProbe, #[case] expected: Option<ContainerRuntime>) {
+        assert_eq!(expected, detect(&probe));
+    }
+}
@@ -0,0 +1,132 @@
+//! Hypervisor detection from the CPUID instruction.
+//!
+//! CPUID is only read from the host on x86_64, where it is available even when `/sys` is masked,
+//! as in most containers.
+
+use crate::{probe::SystemProbe, Hypervisor};
+
+const FEATURES_LEAF: u32 = 0x1;
+// Bit 31 of `ecx` in the features leaf, set when running under a hypervisor.
+const HYPERVISOR_PRESENT: u32 = 1 << 31;
+
+// Leaves where hypervisors report their vendor signature, and the maximum hypervisor leaf in
+// `eax`. Hypervisors emulating Hyper-V, such as KVM and Xen, move their own signature to the next
+// base.
+const HYPERVISOR_LEAF: u32 = 0x4000_0000;
+const NEXT_HYPERVISOR_LEAF: u32 = 0x4000_0100;
+
+// Vendor signatures in `ebx`, `ecx` and `edx`.
+const SIGNATURES: &[(&[u8; 12], Hypervisor)] = &[
+    (b"KVMKVMKVM\0\0\0", Hypervisor::Kvm),
+    (b"Microsoft Hv", Hypervisor::HyperV),
+    (b"XenVMMXenVMM", Hypervisor::Xen),
+    (b"VMwareVMware", Hypervisor::VMware),
+    (b"bhyve bhyve ", Hypervisor::Bhyve),
+    (b"ACRNACRNACRN", Hypervisor::Acrn),
+    // QEMU only reports itself when emulating the CPU with TCG.
+    (b"TCGTCGTCGTCG", Hypervisor::Qemu),
+];
+
+/// Detect the hypervisor the current host is running under, if any.
+pub(crate) fn detect(probe: &(impl SystemProbe + ?Sized)) -> Option<Hypervisor> {
+    let [_, _, ecx, _] = probe.cpuid(FEATURES_LEAF)?;
+    if ecx & HYPERVISOR_PRESENT == 0 {
+        return None;
+    }
+
+    let base = probe.cpuid(HYPERVISOR_LEAF)?;
+    let mut hypervisors: Vec<_> = parse_signature(base).into_iter().collect();
+    // Leaves above the maximum hypervisor leaf return unrelated data.
+    if base[0] >= NEXT_HYPERVISOR_LEAF {
+        hypervisors.extend(probe.cpuid(NEXT_HYPERVISOR_LEAF).and_then(parse_signature));
+    }
+
+    // Hyper-V is only reported by itself if no other hypervisor is found.
+    hypervisors
+        .iter()
+        .find(|hypervisor| **hypervisor != Hypervisor::HyperV)
+        .or(hypervisors.first())
+        .copied()
+}
+
+/// Parse the vendor signature of a hypervisor leaf.
+fn parse_signature([_, ebx, ecx, edx]: [u32; 4]) -> Option<Hypervisor> {
+    let mut signature = [0; 12];
+    signature[0..4].copy_from_slice(&ebx.to_le_bytes());
+    signature[4..8].copy_from_slice(&ecx.to_le_bytes());
+    signature[8..12].copy_from_slice(&edx.to_le_bytes());
+
+    SIGNATURES
+        .iter()
+        .find_map(|(expected, hypervisor)| (**expected == signature).then_some(*hypervisor))
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::FakeProbe;
+
+    fn registers(signature: &[u8; 12]) -> [u32; 4] {
+        registers_with_max_leaf(signature, 0x4000_0001)
+    }
+
+    fn registers_with_max_leaf(signature: &[u8; 12], max_leaf: u32) -> [u32; 4] {
+        let register = |i: usize| u32::from_le_bytes(signature[i..i + 4].try_into().unwrap());
+        [max_leaf, register(0), register(4), register(8)]
+    }
+
+    #[rstest]
+    #[case::kvm(b"KVMKVMKVM\0\0\0", Some(Hypervisor::Kvm))]
+    #[case::hyper_v(b"Microsoft Hv", Some(Hypervisor::HyperV))]
+    #[case::xen(b"XenVMMXenVMM", Some(Hypervisor::Xen))]
+    #[case::vmware(b"VMwareVMware", Some(Hypervisor::VMware))]
+    #[case::bhyve(b"bhyve bhyve ", Some(Hypervisor::Bhyve))]
+    #[case::acrn(b"ACRNACRNACRN", Some(Hypervisor::Acrn))]
+    #[case::tcg(b"TCGTCGTCGTCG", Some(Hypervisor::Qemu))]
+    #[case::unknown(b" lrpepyh  vr", None)]
+    #[case::empty(&[0; 12], None)]
+    fn test_parse_signature(#[case] signature: &[u8; 12], #[case] expected: Option<Hypervisor>) {
+        assert_eq!(expected, parse_signature(registers(signature)));
+    }
+
+    #[rstest]
+    #[case::none(FakeProbe::new(), None)]
+    #[case::kvm(
+        FakeProbe::new()
+            .with_cpuid(FEATURES_LEAF, [0, 0, HYPERVISOR_PRESENT, 0])
+            .with_cpuid(HYPERVISOR_LEAF, registers(b"KVMKVMKVM\0\0\0")),
+        Some(Hypervisor::Kvm)
+    )]
+    #[case::not_present(
+        FakeProbe::new()
+            .with_cpuid(FEATURES_LEAF, [0, 0, 0, 0])
+            .with_cpuid(HYPERVISOR_LEAF, registers(b"KVMKVMKVM\0\0\0")),
+        None
+    )]
+    #[case::hyper_v(
+        FakeProbe::new()
+            .with_cpuid(FEATURES_LEAF, [0, 0, HYPERVISOR_PRESENT, 0])
+            .with_cpuid(HYPERVISOR_LEAF, registers(b"Microsoft Hv"))
+            .with_cpuid(NEXT_HYPERVISOR_LEAF, [0x0000_000b, 0, 0, 0]),
+        Some(Hypervisor::HyperV)
+    )]
+    #[case::xen_viridian(
+        FakeProbe::new()
+            .with_cpuid(FEATURES_LEAF, [0, 0, HYPERVISOR_PRESENT, 0])
+            .with_cpuid(HYPERVISOR_LEAF, registers_with_max_leaf(b"Microsoft Hv", 0x4000_0105))
+            .with_cpuid(NEXT_HYPERVISOR_LEAF, registers(b"XenVMMXenVMM")),
+        Some(Hypervisor::Xen)
+    )]
+    #[case::above_max_leaf(
+        FakeProbe::new()
+            .with_cpuid(FEATURES_LEAF, [0, 0, HYPERVISOR_PRESENT, 0])
+            .with_cpuid(HYPERVISOR_LEAF, registers(b"Microsoft Hv"))
+            .with_cpuid(NEXT_HYPERVISOR_LEAF, registers(b"KVMKVMKVM\0\0\0")),
+        Some(Hypervisor::HyperV)
+    )]
+    fn test_detect(#[case] probe: FakeProbe, #[case] expected: Option<Hypervisor>) {
+        assert_eq!(expected, detect(&probe));
+    }
+}
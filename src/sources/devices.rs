@@ -0,0 +1,100 @@
+//! Cloud provider and hypervisor detection from PCI and VMBus devices.
+
+use std::{collections::BTreeSet, path::Path};
+
+use crate::{probe::SystemProbe, CloudProvider, Hypervisor, Signal};
+
+const PCI_DEVICES: &str = "/sys/bus/pci/devices";
+const VMBUS_DEVICES: &str = "/sys/bus/vmbus/devices";
+
+// PCI vendor IDs of the virtual devices exposed by cloud providers and hypervisors.
+const PCI_VENDORS: &[(&str, Signal)] = &[
+    // Amazon (Annapurna Labs), for the ENA and NVMe devices of EC2 instances.
+    ("0x1d0f", Signal::CloudProvider(CloudProvider::Aws)),
+    // Google, for the gVNIC devices of Compute Engine instances.
+    ("0x1ae0", Signal::CloudProvider(CloudProvider::GoogleCloud)),
+    // Red Hat, for virtio devices.
+    ("0x1af4", Signal::Hypervisor(Hypervisor::Kvm)),
+    ("0x15ad", Signal::Hypervisor(Hypervisor::VMware)),
+    // XenSource, for the Xen platform device of HVM guests.
+    ("0x5853", Signal::Hypervisor(Hypervisor::Xen)),
+];
+
+/// Detect the cloud provider and hypervisor from the devices of the current host.
+///
+/// The VMBus directory exists whenever the Hyper-V drivers are built into the kernel, so only
+/// actual devices are considered.
+pub(crate) fn detect(probe: &(impl SystemProbe + ?Sized)) -> BTreeSet<Signal> {
+    let mut signals: BTreeSet<_> = probe
+        .read_dir(Path::new(PCI_DEVICES))
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|device| probe.read_file(&device.join("vendor")))
+        .filter_map(|vendor| parse_vendor(&String::from_utf8_lossy(&vendor)))
+        .collect();
+
+    if probe
+        .read_dir(Path::new(VMBUS_DEVICES))
+        .is_some_and(|devices| !devices.is_empty())
+    {
+        signals.insert(Signal::Hypervisor(Hypervisor::HyperV));
+    }
+
+    signals
+}
+
+/// Parse the content of the `vendor` file of a PCI device.
+fn parse_vendor(vendor: &str) -> Option<Signal> {
+    let vendor = vendor.trim().to_lowercase();
+
+    PCI_VENDORS
+        .iter()
+        .find_map(|(id, signal)| (*id == vendor).then_some(*signal))
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::FakeProbe;
+
+    #[rstest]
+    #[case::amazon("0x1d0f\n", Some(Signal::CloudProvider(CloudProvider::Aws)))]
+    #[case::google("0x1ae0\n", Some(Signal::CloudProvider(CloudProvider::GoogleCloud)))]
+    #[case::virtio("0x1af4\n", Some(Signal::Hypervisor(Hypervisor::Kvm)))]
+    #[case::vmware("0x15AD\n", Some(Signal::Hypervisor(Hypervisor::VMware)))]
+    #[case::xen("0x5853\n", Some(Signal::Hypervisor(Hypervisor::Xen)))]
+    #[case::intel("0x8086\n", None)]
+    #[case::empty("", None)]
+    fn test_parse_vendor(#[case] vendor: &str, #[case] expected: Option<Signal>) {
+        assert_eq!(expected, parse_vendor(vendor));
+    }
+
+    #[rstest]
+    #[case::none(FakeProbe::new(), &[])]
+    #[case::aws_nitro(
+        FakeProbe::new()
+            .with_file("/sys/bus/pci/devices/0000:00:00.0/vendor", "0x8086\n")
+            .with_file("/sys/bus/pci/devices/0000:00:04.0/vendor", "0x1d0f\n")
+            .with_file("/sys/bus/pci/devices/0000:00:05.0/vendor", "0x1d0f\n"),
+        &[Signal::CloudProvider(CloudProvider::Aws)]
+    )]
+    #[case::gcp(
+        FakeProbe::new()
+            .with_file("/sys/bus/pci/devices/0000:00:03.0/vendor", "0x1af4\n")
+            .with_file("/sys/bus/pci/devices/0000:00:04.0/vendor", "0x1ae0\n"),
+        &[
+            Signal::Hypervisor(Hypervisor::Kvm),
+            Signal::CloudProvider(CloudProvider::GoogleCloud),
+        ]
+    )]
+    #[case::hyper_v(
+        FakeProbe::new()
+            .with_file("/sys/bus/vmbus/devices/f8b3781b-1e82-4818-a1c3-63d806ec15bb/class_id", ""),
+        &[Signal::Hypervisor(Hypervisor::HyperV)]
+    )]
+    fn test_detect(#[case] probe: FakeProbe, #[case] expected: &[Signal]) {
+        assert_eq!(expected, detect(&probe).into_iter().collect::<Vec<_>>());
+    }
+}
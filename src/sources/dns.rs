@@ -0,0 +1,209 @@
+//! Cloud provider and orchestrator detection from the resolver configuration.
+//!
+//! Only the local configuration files are read, no DNS query is ever made. Containers usually
+//! inherit the search domains and nameservers of their host.
+
+use std::collections::BTreeSet;
+
+use super::read_to_string;
+use crate::{probe::SystemProbe, CloudProvider, Orchestrator, Signal};
+
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+const HOSTS: &str = "/etc/hosts";
+
+// Search domains, matched along with their subdomains.
+const DOMAINS: &[(&str, Signal)] = &[
+    ("ec2.internal", Signal::CloudProvider(CloudProvider::Aws)),
+    (
+        "compute.internal",
+        Signal::CloudProvider(CloudProvider::Aws),
+    ),
+    (
+        "google.internal",
+        Signal::CloudProvider(CloudProvider::GoogleCloud),
+    ),
+    (
+        "reddog.microsoft.com",
+        Signal::CloudProvider(CloudProvider::Azure),
+    ),
+    (
+        "internal.cloudapp.net",
+        Signal::CloudProvider(CloudProvider::Azure),
+    ),
+    (
+        "svc.cluster.local",
+        Signal::Orchestrator(Orchestrator::Kubernetes),
+    ),
+    // Consul also serves workloads outside of Nomad.
+    ("consul", Signal::OrchestratorHint(Orchestrator::Nomad)),
+];
+
+// Nameservers provided by the cloud providers. The GCP nameserver is left out, as other clouds
+// such as Oracle Cloud also serve DNS on the metadata address.
+const NAMESERVERS: &[(&str, Signal)] = &[
+    ("169.254.169.253", Signal::CloudProvider(CloudProvider::Aws)),
+    ("fd00:ec2::253", Signal::CloudProvider(CloudProvider::Aws)),
+    ("168.63.129.16", Signal::CloudProvider(CloudProvider::Azure)),
+];
+
+// Host names of `/etc/hosts`.
+const HOST_NAMES: &[(&str, Signal)] = &[(
+    "metadata.google.internal",
+    Signal::CloudProvider(CloudProvider::GoogleCloud),
+)];
+
+/// Detect the cloud provider and orchestrator from `/etc/resolv.conf` and `/etc/hosts`.
+pub(crate) fn detect(probe: &(impl SystemProbe + ?Sized)) -> BTreeSet<Signal> {
+    let mut signals = BTreeSet::new();
+
+    if let Some(content) = read_to_string(probe, RESOLV_CONF) {
+        signals.extend(parse_resolv_conf(&content));
+    }
+    if let Some(content) = read_to_string(probe, HOSTS) {
+        signals.extend(parse_hosts(&content));
+    }
+
+    signals
+}
+
+/// Parse the content of `/etc/resolv.conf`.
+fn parse_resolv_conf(content: &str) -> BTreeSet<Signal> {
+    let mut signals = BTreeSet::new();
+
+    for line in content.lines() {
+        let line = line.split(['#', ';']).next().unwrap_or_default();
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("search" | "domain") => signals.extend(words.filter_map(parse_domain)),
+            Some("nameserver") => signals.extend(words.next().and_then(|nameserver| {
+                NAMESERVERS
+                    .iter()
+                    .find_map(|(address, signal)| (*address == nameserver).then_some(*signal))
+            })),
+            _ => {}
+        }
+    }
+
+    signals
+}
+
+/// Parse a search domain.
+fn parse_domain(domain: &str) -> Option<Signal> {
+    let domain = domain.trim_end_matches('.').to_lowercase();
+
+    // GCP instances search their project domain, such as `c.my-project.internal`.
+    let labels: Vec<_> = domain.split('.').collect();
+    if let ["c", _, "internal"] = labels.as_slice() {
+        return Some(Signal::CloudProvider(CloudProvider::GoogleCloud));
+    }
+
+    DOMAINS.iter().find_map(|(suffix, signal)| {
+        let matches = domain == *suffix
+            || domain
+                .strip_suffix(suffix)
+                .is_some_and(|prefix| prefix.ends_with('.'));
+        matches.then_some(*signal)
+    })
+}
+
+/// Parse the content of `/etc/hosts`.
+fn parse_hosts(content: &str) -> BTreeSet<Signal> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .flat_map(|line| line.split_whitespace().skip(1))
+        .filter_map(|host_name| {
+            let host_name = host_name.to_lowercase();
+            HOST_NAMES
+                .iter()
+                .find_map(|(expected, signal)| (*expected == host_name).then_some(*signal))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::FakeProbe;
+
+    const AWS: Signal = Signal::CloudProvider(CloudProvider::Aws);
+    const AZURE: Signal = Signal::CloudProvider(CloudProvider::Azure);
+    const GCP: Signal = Signal::CloudProvider(CloudProvider::GoogleCloud);
+    const KUBERNETES: Signal = Signal::Orchestrator(Orchestrator::Kubernetes);
+    const NOMAD: Signal = Signal::OrchestratorHint(Orchestrator::Nomad);
+
+    #[rstest]
+    #[case::aws_us_east_1("ec2.internal", Some(AWS))]
+    #[case::aws_region("us-west-2.compute.internal", Some(AWS))]
+    #[case::gcp_project("c.my-project.internal", Some(GCP))]
+    #[case::gcp("google.internal", Some(GCP))]
+    #[case::azure("reddog.microsoft.com", Some(AZURE))]
+    #[case::azure_vnet("abc123.bx.internal.cloudapp.net", Some(AZURE))]
+    #[case::kubernetes_namespace("default.svc.cluster.local", Some(KUBERNETES))]
+    #[case::kubernetes("svc.cluster.local", Some(KUBERNETES))]
+    #[case::cluster("cluster.local", None)]
+    #[case::consul("service.consul", Some(NOMAD))]
+    #[case::trailing_dot("ec2.internal.", Some(AWS))]
+    #[case::partial_label("myec2.internal", None)]
+    #[case::other_internal("c.my-project.other.internal", None)]
+    #[case::corp("corp.example.com", None)]
+    fn test_parse_domain(#[case] domain: &str, #[case] expected: Option<Signal>) {
+        assert_eq!(expected, parse_domain(domain));
+    }
+
+    #[rstest]
+    #[case::aws(
+        "; generated by /usr/sbin/dhclient-script\nsearch us-west-2.compute.internal\noptions timeout:2 attempts:5\nnameserver 10.0.0.2\n",
+        &[AWS]
+    )]
+    #[case::gcp(
+        "domain c.my-project.internal\nsearch c.my-project.internal. google.internal.\nnameserver 169.254.169.254\n",
+        &[GCP]
+    )]
+    #[case::azure(
+        "nameserver 127.0.0.53\noptions edns0 trust-ad\nsearch abc123.bx.internal.cloudapp.net\n",
+        &[AZURE]
+    )]
+    #[case::azure_nameserver("nameserver 168.63.129.16\n", &[AZURE])]
+    #[case::metadata_nameserver("nameserver 169.254.169.254\n", &[])]
+    #[case::eks(
+        "search default.svc.cluster.local svc.cluster.local cluster.local ec2.internal\nnameserver 172.20.0.10\noptions ndots:5\n",
+        &[KUBERNETES, AWS]
+    )]
+    #[case::comment("# search ec2.internal\nnameserver 1.1.1.1 # 169.254.169.253\n", &[])]
+    #[case::empty("", &[])]
+    fn test_parse_resolv_conf(#[case] content: &str, #[case] expected: &[Signal]) {
+        assert_eq!(
+            expected,
+            parse_resolv_conf(content).into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[rstest]
+    #[case::gcp(
+        "127.0.0.1 localhost\n169.254.169.254 metadata.google.internal metadata  # Added by Google\n",
+        &[GCP]
+    )]
+    #[case::comment("# 169.254.169.254 metadata.google.internal\n", &[])]
+    #[case::localhost("127.0.0.1 localhost\n::1 localhost ip6-localhost\n", &[])]
+    fn test_parse_hosts(#[case] content: &str, #[case] expected: &[Signal]) {
+        assert_eq!(
+            expected,
+            parse_hosts(content).into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[rstest]
+    fn test_detect() {
+        let probe = FakeProbe::new()
+            .with_file(RESOLV_CONF, "search service.consul\nnameserver 10.0.0.2\n")
+            .with_file(HOSTS, "169.254.169.254 metadata.google.internal\n");
+
+        assert_eq!(
+            vec![GCP, NOMAD],
+            detect(&probe).into_iter().collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,53 @@
+//! Platform detection from the values of environment variables.
+
+use std::collections::BTreeSet;
+
+use crate::{probe::SystemProbe, EnvValue, Signal};
+
+/// Detect the environment variable values identifying a platform.
+///
+/// Values are compared in place through [`SystemProbe::env_starts_with`], and never copied.
+pub(crate) fn detect(probe: &(impl SystemProbe + ?Sized)) -> BTreeSet<Signal> {
+    EnvValue::ALL
+        .iter()
+        .filter(|env_value| probe.env_starts_with(env_value.name(), env_value.prefix()))
+        .map(|env_value| Signal::EnvValue(*env_value))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::FakeProbe;
+
+    #[rstest]
+    #[case::none(FakeProbe::new(), &[])]
+    #[case::name_only(FakeProbe::new().with_env("AWS_EXECUTION_ENV"), &[])]
+    #[case::fargate(
+        FakeProbe::new().with_env_value("AWS_EXECUTION_ENV", "AWS_ECS_FARGATE"),
+        &[EnvValue::AwsEcsFargate]
+    )]
+    #[case::ecs_ec2(
+        FakeProbe::new().with_env_value("AWS_EXECUTION_ENV", "AWS_ECS_EC2"),
+        &[EnvValue::AwsEcsEc2]
+    )]
+    #[case::lambda(
+        FakeProbe::new().with_env_value("AWS_EXECUTION_ENV", "AWS_Lambda_python3.12"),
+        &[EnvValue::AwsLambda]
+    )]
+    #[case::other(
+        FakeProbe::new().with_env_value("AWS_EXECUTION_ENV", "CloudShell"),
+        &[]
+    )]
+    fn test_detect(#[case] probe: FakeProbe, #[case] expected: &[EnvValue]) {
+        assert_eq!(
+            expected
+                .iter()
+                .map(|env_value| Signal::EnvValue(*env_value))
+                .collect::<Vec<_>>(),
+            detect(&probe).into_iter().collect::<Vec<_>>()
+        );
+    }
+}
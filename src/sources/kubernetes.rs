@@ -0,0 +1,70 @@
+//! Kubernetes detection from the files set up by the kubelet.
+//!
+//! These complement the `KUBERNETES_SERVICE_*` environment variables, which are missing when a pod
+//! sets `enableServiceLinks: false`.
+
+use std::path::Path;
+
+use crate::{probe::SystemProbe, Orchestrator};
+
+// Only the existence of the directory is checked, as it holds the service account token.
+const SERVICE_ACCOUNT: &str = "/var/run/secrets/kubernetes.io/serviceaccount";
+const HOSTS: &str = "/etc/hosts";
+const HOSTS_HEADER: &str = "# Kubernetes-managed hosts file";
+const TERMINATION_LOG: &str = "/dev/termination-log";
+
+/// Detect whether the current process runs in a Kubernetes pod.
+pub(crate) fn detect(probe: &(impl SystemProbe + ?Sized)) -> Option<Orchestrator> {
+    let detected = probe.exists(Path::new(SERVICE_ACCOUNT))
+        || probe.exists(Path::new(TERMINATION_LOG))
+        || probe
+            .read_file(Path::new(HOSTS))
+            .is_some_and(|hosts| is_managed_hosts(&hosts));
+
+    detected.then_some(Orchestrator::Kubernetes)
+}
+
+/// Returns `true` if the hosts file was written by the kubelet.
+fn is_managed_hosts(content: &[u8]) -> bool {
+    String::from_utf8_lossy(content)
+        .lines()
+        .any(|line| line.trim_start().starts_with(HOSTS_HEADER))
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::FakeProbe;
+
+    const MANAGED_HOSTS: &str = "\
+# Kubernetes-managed hosts file.
+127.0.0.1\tlocalhost
+::1\tlocalhost ip6-localhost ip6-loopback
+10.8.0.12\tmy-pod-5d9c7b8f4-x2x7k
+";
+    const HOSTS_FILE: &str = "\
+127.0.0.1\tlocalhost
+::1\tlocalhost ip6-localhost ip6-loopback
+";
+
+    #[rstest]
+    #[case::none(FakeProbe::new(), None)]
+    #[case::service_account(
+        FakeProbe::new().with_file(format!("{SERVICE_ACCOUNT}/namespace"), "default"),
+        Some(Orchestrator::Kubernetes)
+    )]
+    #[case::managed_hosts(
+        FakeProbe::new().with_file(HOSTS, MANAGED_HOSTS),
+        Some(Orchestrator::Kubernetes)
+    )]
+    #[case::hosts(FakeProbe::new().with_file(HOSTS, HOSTS_FILE), None)]
+    #[case::termination_log(
+        FakeProbe::new().with_file(TERMINATION_LOG, ""),
+        Some(Orchestrator::Kubernetes)
+    )]
+    fn test_detect(#[case] probe: FakeProbe, #[case] expected: Option<Orchestrator>) {
+        assert_eq!(expected, detect(&probe));
+    }
+}
@@ -0,0 +1,129 @@
+//! Cloud provider and hypervisor detection from the MAC addresses of network interfaces.
+
+use std::{collections::BTreeSet, net::Ipv4Addr, path::Path};
+
+use crate::{probe::SystemProbe, CloudProvider, Hypervisor, Signal};
+
+const NET_INTERFACES: &str = "/sys/class/net";
+
+// Prefixes of the MAC addresses assigned by cloud providers and hypervisors, other than Google
+// Cloud.
+const MAC_PREFIXES: &[(&str, Signal)] = &[
+    ("00:0d:3a", Signal::CloudProvider(CloudProvider::Azure)),
+    ("00:15:5d", Signal::Hypervisor(Hypervisor::HyperV)),
+    // Default prefix of QEMU, mostly used with KVM.
+    ("52:54:00", Signal::Hypervisor(Hypervisor::Kvm)),
+    ("00:50:56", Signal::Hypervisor(Hypervisor::VMware)),
+    ("00:0c:29", Signal::Hypervisor(Hypervisor::VMware)),
+    ("00:16:3e", Signal::Hypervisor(Hypervisor::Xen)),
+];
+
+/// Detect the cloud provider and hypervisor from the network interfaces of the current host.
+///
+/// Only interfaces backed by a device are considered, which skips the loopback, bridges and other
+/// virtual interfaces, whose addresses are random or derived from other interfaces.
+pub(crate) fn detect(probe: &(impl SystemProbe + ?Sized)) -> BTreeSet<Signal> {
+    probe
+        .read_dir(Path::new(NET_INTERFACES))
+        .unwrap_or_default()
+        .iter()
+        .filter(|interface| probe.exists(&interface.join("device")))
+        .filter_map(|interface| probe.read_file(&interface.join("address")))
+        .filter_map(|address| parse_address(&String::from_utf8_lossy(&address)))
+        .collect()
+}
+
+/// Parse the content of the `address` file of a network interface.
+fn parse_address(address: &str) -> Option<Signal> {
+    let address = address.trim().to_lowercase();
+    if is_google_cloud(&address) {
+        return Some(Signal::CloudProvider(CloudProvider::GoogleCloud));
+    }
+
+    MAC_PREFIXES
+        .iter()
+        .find_map(|(prefix, signal)| address.starts_with(prefix).then_some(*signal))
+}
+
+/// Returns `true` for the MAC addresses assigned by Google Cloud, which are `42:01` followed by the
+/// private IPv4 address of the interface.
+fn is_google_cloud(address: &str) -> bool {
+    let Some(octets) = address.strip_prefix("42:01:") else {
+        return false;
+    };
+    let octets: Option<Vec<u8>> = octets
+        .split(':')
+        .map(|octet| u8::from_str_radix(octet, 16).ok())
+        .collect();
+
+    match octets.as_deref() {
+        Some(&[a, b, c, d]) => Ipv4Addr::new(a, b, c, d).is_private(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::FakeProbe;
+
+    #[rstest]
+    #[case::gcp(
+        "42:01:0a:80:00:02\n",
+        Some(Signal::CloudProvider(CloudProvider::GoogleCloud))
+    )]
+    #[case::gcp_172_16(
+        "42:01:ac:10:00:02\n",
+        Some(Signal::CloudProvider(CloudProvider::GoogleCloud))
+    )]
+    #[case::gcp_172_31(
+        "42:01:ac:1f:ff:02\n",
+        Some(Signal::CloudProvider(CloudProvider::GoogleCloud))
+    )]
+    #[case::gcp_192_168(
+        "42:01:c0:a8:01:02\n",
+        Some(Signal::CloudProvider(CloudProvider::GoogleCloud))
+    )]
+    #[case::gcp_172_32("42:01:ac:20:00:02\n", None)]
+    #[case::gcp_public("42:01:08:08:08:08\n", None)]
+    #[case::gcp_short("42:01:0a:80:00\n", None)]
+    #[case::azure(
+        "00:0D:3A:1B:2C:3D\n",
+        Some(Signal::CloudProvider(CloudProvider::Azure))
+    )]
+    #[case::hyper_v("00:15:5d:01:02:03\n", Some(Signal::Hypervisor(Hypervisor::HyperV)))]
+    #[case::qemu("52:54:00:12:34:56\n", Some(Signal::Hypervisor(Hypervisor::Kvm)))]
+    #[case::vmware("00:50:56:a1:b2:c3\n", Some(Signal::Hypervisor(Hypervisor::VMware)))]
+    #[case::vmware_generated("00:0c:29:a1:b2:c3\n", Some(Signal::Hypervisor(Hypervisor::VMware)))]
+    #[case::xen("00:16:3e:a1:b2:c3\n", Some(Signal::Hypervisor(Hypervisor::Xen)))]
+    #[case::docker("02:42:ac:11:00:02\n", None)]
+    #[case::loopback("00:00:00:00:00:00\n", None)]
+    fn test_parse_address(#[case] address: &str, #[case] expected: Option<Signal>) {
+        assert_eq!(expected, parse_address(address));
+    }
+
+    #[rstest]
+    #[case::none(FakeProbe::new(), &[])]
+    #[case::gcp(
+        FakeProbe::new()
+            .with_file("/sys/class/net/lo/address", "00:00:00:00:00:00\n")
+            .with_file("/sys/class/net/ens4/address", "42:01:0a:80:00:02\n")
+            .with_file("/sys/class/net/ens4/device/vendor", "0x1af4\n"),
+        &[Signal::CloudProvider(CloudProvider::GoogleCloud)]
+    )]
+    #[case::virtual_only(
+        FakeProbe::new()
+            .with_file("/sys/class/net/virbr0/address", "52:54:00:12:34:56\n")
+            .with_file("/sys/class/net/virbr0/bridge/bridge_id", "8000.525400123456\n"),
+        &[]
+    )]
+    #[case::container(
+        FakeProbe::new().with_file("/sys/class/net/eth0/address", "02:42:ac:11:00:02\n"),
+        &[]
+    )]
+    fn test_detect(#[case] probe: FakeProbe, #[case] expected: &[Signal]) {
+        assert_eq!(expected, detect(&probe).into_iter().collect::<Vec<_>>());
+    }
+}
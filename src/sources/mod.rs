@@ -0,0 +1,59 @@
+//! Detection sources other than SMBIOS data and the presence of environment variables.
+//!
+//! Each source reads from a [`SystemProbe`] and produces [`Signal`]s, which are stored in the
+//! [`DetectionSnapshot`](crate::DetectionSnapshot) and scored by the detectors.
+
+use std::{collections::BTreeSet, path::Path};
+
+use crate::{probe::SystemProbe, Hypervisor, Signal};
+
+mod arm;
+mod block;
+mod cloud_init;
+mod container;
+mod cpuid;
+mod devices;
+mod dns;
+mod env_values;
+mod kubernetes;
+mod mac;
+mod xen;
+
+/// Read the signals from all sources.
+pub(crate) fn read(probe: &(impl SystemProbe + ?Sized)) -> BTreeSet<Signal> {
+    let mut signals = BTreeSet::new();
+
+    if let Some(container_runtime) = container::detect(probe) {
+        signals.insert(Signal::ContainerRuntime(container_runtime));
+    }
+    if let Some(orchestrator) = kubernetes::detect(probe) {
+        signals.insert(Signal::Orchestrator(orchestrator));
+    }
+    if let Some(hypervisor) = cpuid::detect(probe) {
+        signals.insert(Signal::Hypervisor(hypervisor));
+    }
+    if xen::detect(probe) {
+        signals.insert(Signal::Hypervisor(Hypervisor::Xen));
+    }
+    if let Some(cloud_provider) = xen::cloud_provider(probe) {
+        signals.insert(Signal::CloudProvider(cloud_provider));
+    }
+    if let Some(cloud_provider) = cloud_init::detect(probe) {
+        signals.insert(Signal::CloudProvider(cloud_provider));
+    }
+    signals.extend(devices::detect(probe));
+    signals.extend(mac::detect(probe));
+    signals.extend(dns::detect(probe));
+    signals.extend(block::detect(probe));
+    signals.extend(env_values::detect(probe));
+    signals.extend(arm::detect(probe));
+
+    signals
+}
+
+/// Read a file of the probe as UTF-8 text.
+fn read_to_string(probe: &(impl SystemProbe + ?Sized), path: &str) -> Option<String> {
+    probe
+        .read_file(Path::new(path))
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+}
@@ -0,0 +1,73 @@
+//! Xen guest detection from `/sys/hypervisor` and `/proc/xen`.
+
+use std::path::Path;
+
+use super::read_to_string;
+use crate::{probe::SystemProbe, CloudProvider};
+
+const HYPERVISOR_TYPE: &str = "/sys/hypervisor/type";
+const HYPERVISOR_UUID: &str = "/sys/hypervisor/uuid";
+const PROC_XEN: &str = "/proc/xen";
+
+// Prefix of the domain UUID of EC2 instances running on Xen.
+const EC2_UUID_PREFIX: &str = "ec2";
+
+/// Returns `true` if the current host is a Xen guest.
+pub(crate) fn detect(probe: &(impl SystemProbe + ?Sized)) -> bool {
+    read_trimmed(probe, HYPERVISOR_TYPE).is_some_and(|hypervisor| hypervisor == "xen")
+        || probe.exists(Path::new(PROC_XEN))
+}
+
+/// Detect the cloud provider running the current Xen guest, if any.
+///
+/// Xen-based EC2 instances report "Xen" as their SMBIOS vendor, but their domain UUID always
+/// starts with `ec2`.
+pub(crate) fn cloud_provider(probe: &(impl SystemProbe + ?Sized)) -> Option<CloudProvider> {
+    read_trimmed(probe, HYPERVISOR_UUID)
+        .is_some_and(|uuid| uuid.starts_with(EC2_UUID_PREFIX))
+        .then_some(CloudProvider::Aws)
+}
+
+fn read_trimmed(probe: &(impl SystemProbe + ?Sized), path: &str) -> Option<String> {
+    read_to_string(probe, path).map(|content| content.trim().to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::FakeProbe;
+
+    #[rstest]
+    #[case::none(FakeProbe::new(), false, None)]
+    #[case::ec2(
+        FakeProbe::new()
+            .with_file(HYPERVISOR_TYPE, "xen\n")
+            .with_file(HYPERVISOR_UUID, "ec2e1916-9099-7caf-fd21-012345abcdef\n"),
+        true,
+        Some(CloudProvider::Aws)
+    )]
+    #[case::ec2_uppercase(
+        FakeProbe::new().with_file(HYPERVISOR_UUID, "EC2E1916-9099-7CAF-FD21-012345ABCDEF\n"),
+        false,
+        Some(CloudProvider::Aws)
+    )]
+    #[case::xen(
+        FakeProbe::new()
+            .with_file(HYPERVISOR_TYPE, "xen\n")
+            .with_file(HYPERVISOR_UUID, "4f1a4b2c-9099-7caf-fd21-012345abcdef\n"),
+        true,
+        None
+    )]
+    #[case::proc_xen(FakeProbe::new().with_file("/proc/xen/capabilities", ""), true, None)]
+    #[case::other_type(FakeProbe::new().with_file(HYPERVISOR_TYPE, "zvm\n"), false, None)]
+    fn test_detect(
+        #[case] probe: FakeProbe,
+        #[case] expected: bool,
+        #[case] expected_cloud_provider: Option<CloudProvider>,
+    ) {
+        assert_eq!(expected, detect(&probe));
+        assert_eq!(expected_cloud_provider, cloud_provider(&probe));
+    }
+}
@@ -0,0 +1,647 @@
+use std::fmt::Display;
+
+use crate::{
+    Candidate, CloudProvider, ComputeEnvironment, CustomEnvironment, DetectionReport, Signal,
+};
+
+/// Detected compute environment, split into independent layers.
+///
+/// Each layer comes with its own confidence, using the same scale as the detection threshold. It
+/// is derived from the evidence supporting that layer: the SMBIOS data and platform signals for
+/// the cloud provider and hypervisor, the environment variables and other signals for the
+/// orchestrator, and the weight of the observed signal for the container runtime, hypervisor and
+/// CPU family. The managed platform relies on all the evidence.
+///
+/// # Examples
+///
+/// ```
+/// use apollo_environment_detector::{detect_stack, MAX_INDIVIDUAL_WEIGHTING};
+///
+/// let stack = detect_stack(MAX_INDIVIDUAL_WEIGHTING);
+/// if let Some(orchestrator) = stack.orchestrator {
+///     println!("{} ({})", orchestrator.value, orchestrator.confidence);
+/// }
+///
+/// // The flat compute environment can be derived from the stack.
+/// println!("{:?}", stack.compute_environment());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct EnvironmentStack {
+    /// Cloud provider.
+    pub cloud_provider: Option<Layer<CloudProvider>>,
+    /// Managed compute platform offered by the cloud provider.
+    pub platform: Option<Layer<ManagedPlatform>>,
+    /// Workload orchestrator.
+    pub orchestrator: Option<Layer<Orchestrator>>,
+    /// Container runtime.
+    pub container_runtime: Option<Layer<ContainerRuntime>>,
+    /// Hypervisor.
+    pub hypervisor: Option<Layer<Hypervisor>>,
+    /// CPU family.
+    pub cpu_family: Option<Layer<CpuFamily>>,
+}
+
+impl EnvironmentStack {
+    /// [`ComputeEnvironment`] corresponding to this stack.
+    pub fn compute_environment(&self) -> Option<ComputeEnvironment> {
+        let cloud_provider = self.cloud_provider.map(|layer| layer.value);
+        let platform = self.platform.map(|layer| layer.value);
+        let orchestrator = self.orchestrator.map(|layer| layer.value);
+        let hypervisor = self.hypervisor.map(|layer| layer.value);
+
+        match (platform, orchestrator) {
+            (Some(ManagedPlatform::AwsEc2), Some(Orchestrator::Nomad)) => {
+                Some(ComputeEnvironment::AwsNomad)
+            }
+            (Some(ManagedPlatform::AzureVM), Some(Orchestrator::Nomad)) => {
+                Some(ComputeEnvironment::AzureNomad)
+            }
+            (Some(ManagedPlatform::GcpComputeEngine), Some(Orchestrator::Nomad)) => {
+                Some(ComputeEnvironment::GcpNomad)
+            }
+            (Some(platform), _) => Some(platform.compute_environment()),
+            (None, Some(Orchestrator::Kubernetes)) => Some(match cloud_provider {
+                Some(CloudProvider::Aws) => ComputeEnvironment::AwsKubernetes,
+                Some(CloudProvider::Azure) => ComputeEnvironment::AzureKubernetes,
+                Some(CloudProvider::GoogleCloud) => ComputeEnvironment::GcpKubernetes,
+                None => ComputeEnvironment::Kubernetes,
+            }),
+            (None, Some(Orchestrator::Nomad)) => Some(match cloud_provider {
+                Some(CloudProvider::Aws) => ComputeEnvironment::AwsNomad,
+                Some(CloudProvider::Azure) => ComputeEnvironment::AzureNomad,
+                Some(CloudProvider::GoogleCloud) => ComputeEnvironment::GcpNomad,
+                None => ComputeEnvironment::Nomad,
+            }),
+            (None, None) => match (cloud_provider, hypervisor) {
+                (Some(CloudProvider::Aws), _) => Some(ComputeEnvironment::AwsEc2),
+                (Some(CloudProvider::Azure), _) => Some(ComputeEnvironment::AzureVM),
+                (Some(CloudProvider::GoogleCloud), _) => Some(ComputeEnvironment::GcpComputeEngine),
+                (None, Some(Hypervisor::Qemu)) => Some(ComputeEnvironment::Qemu),
+                (None, _) => None,
+            },
+        }
+    }
+}
+
+impl From<&DetectionReport> for EnvironmentStack {
+    fn from(report: &DetectionReport) -> Self {
+        // The container runtime, the hypervisor and the CPU family are observed directly,
+        // regardless of the compute environment.
+        let mut stack = Self {
+            container_runtime: observed(&report.signals, Signal::container_runtime),
+            hypervisor: observed(&report.signals, Signal::hypervisor),
+            cpu_family: observed(&report.signals, Signal::cpu_family),
+            ..Self::default()
+        };
+
+        let mut candidates = report.candidates.iter();
+
+        // The most likely candidate defines the shape of the stack. Each layer is only as
+        // confident as the part of the evidence supporting it, while the managed platform needs
+        // all of it.
+        let Some(best) = candidates.next() else {
+            return stack;
+        };
+        let environment = best.environment;
+        stack.cloud_provider = Layer::new(
+            environment.cloud_provider(),
+            cloud_provider_confidence(best),
+        );
+        stack.platform = Layer::new(environment.platform(), best.score);
+        stack.orchestrator = Layer::new(environment.orchestrator(), runtime_confidence(best));
+        stack.hypervisor =
+            Layer::new(environment.hypervisor(), platform_confidence(best)).or(stack.hypervisor);
+
+        // Other candidates can only complete layers that don't change the compute environment, as
+        // long as they don't contradict the cloud provider.
+        for candidate in candidates {
+            let environment = candidate.environment;
+            if environment.cloud_provider().is_some()
+                && environment.cloud_provider() != stack.cloud_provider.map(|layer| layer.value)
+            {
+                continue;
+            }
+
+            if stack.hypervisor.is_none() {
+                stack.hypervisor =
+                    Layer::new(environment.hypervisor(), platform_confidence(candidate));
+            }
+        }
+
+        stack
+    }
+}
+
+/// Layer observed from a signal, as confident as the signal weighs in the score.
+fn observed<T>(signals: &[Signal], value: impl Fn(&Signal) -> Option<T>) -> Option<Layer<T>> {
+    signals
+        .iter()
+        .find_map(|signal| Layer::new(value(signal), signal.weight()))
+}
+
+/// Confidence from the SMBIOS data and the platform signals.
+fn platform_confidence(candidate: &Candidate) -> u16 {
+    candidate.smbios.score.max(candidate.signals.platform_score)
+}
+
+/// Confidence from the environment variables and the other signals.
+fn runtime_confidence(candidate: &Candidate) -> u16 {
+    candidate
+        .env_vars
+        .score
+        .max(candidate.signals.runtime_score)
+}
+
+/// The cloud provider is supported by the SMBIOS data and the platform signals, or by the
+/// environment variables of environments expecting no SMBIOS data, such as managed container
+/// platforms.
+fn cloud_provider_confidence(candidate: &Candidate) -> u16 {
+    let smbios = &candidate.smbios;
+    let expects_smbios =
+        !(smbios.matched.is_empty() && smbios.missing.is_empty() && smbios.unavailable.is_empty());
+
+    if expects_smbios || candidate.signals.platform_score > 0 {
+        platform_confidence(candidate)
+    } else {
+        runtime_confidence(candidate)
+    }
+}
+
+/// Single layer of an [`EnvironmentStack`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Layer<T> {
+    /// Detected value for this layer.
+    pub value: T,
+    /// Confidence for this layer, using the same scale as the detection threshold.
+    pub confidence: u16,
+}
+
+impl<T> Layer<T> {
+    fn new(value: Option<T>, confidence: u16) -> Option<Self> {
+        value.map(|value| Self { value, confidence })
+    }
+}
+
+/// Managed compute platforms that can be detected by this crate.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum ManagedPlatform {
+    /// Amazon Elastic Compute Cloud (EC2)
+    AwsEc2,
+    /// Amazon Elastic Container Service (ECS)
+    AwsEcs,
+    /// AWS Lambda
+    AwsLambda,
+    /// Amazon Elastic Kubernetes Service (EKS)
+    AwsEks,
+    /// Azure Containers Apps
+    AzureContainerApps,
+    /// Azure Container Apps Job
+    AzureContainerAppsJob,
+    /// Azure Container Instance
+    AzureContainerInstance,
+    /// Azure Kubernetes Service (AKS)
+    AzureAks,
+    /// Azure VM
+    AzureVM,
+    /// Google Cloud Run (Gen1)
+    GcpCloudRunGen1,
+    /// Google Cloud Run (Gen2)
+    GcpCloudRunGen2,
+    /// Google Cloud Run (Job)
+    GcpCloudRunJob,
+    /// Google Compute Engine
+    GcpComputeEngine,
+    /// Google Kubernetes Engine (GKE)
+    GcpKubernetesEngine,
+    /// User-defined environment
+    Custom(CustomEnvironment),
+}
+
+impl ManagedPlatform {
+    /// Static str representation of the [`ManagedPlatform`].
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AwsEc2 => "AWS EC2",
+            Self::AwsEcs => "AWS ECS",
+            Self::AwsLambda => "AWS Lambda",
+            Self::AwsEks => "AWS EKS",
+            Self::AzureContainerApps => "Azure Container Apps",
+            Self::AzureContainerAppsJob => "Azure Container Apps Job",
+            Self::AzureContainerInstance => "Azure Container Instance",
+            Self::AzureAks => "Azure AKS",
+            Self::AzureVM => "Azure VM",
+            Self::GcpCloudRunGen1 => "Google Cloud Run (Gen1)",
+            Self::GcpCloudRunGen2 => "Google Cloud Run (Gen2)",
+            Self::GcpCloudRunJob => "Google Cloud Run (Job)",
+            Self::GcpComputeEngine => "Google Compute Engine",
+            Self::GcpKubernetesEngine => "Google Kubernetes Engine",
+            Self::Custom(custom) => ComputeEnvironment::Custom(*custom).as_str(),
+        }
+    }
+
+    pub(crate) fn compute_environment(&self) -> ComputeEnvironment {
+        match self {
+            Self::AwsEc2 => ComputeEnvironment::AwsEc2,
+            Self::AwsEcs => ComputeEnvironment::AwsEcs,
+            Self::AwsLambda => ComputeEnvironment::AwsLambda,
+            Self::AwsEks => ComputeEnvironment::AwsKubernetes,
+            Self::AzureContainerApps => ComputeEnvironment::AzureContainerApps,
+            Self::AzureContainerAppsJob => ComputeEnvironment::AzureContainerAppsJob,
+            Self::AzureContainerInstance => ComputeEnvironment::AzureContainerInstance,
+            Self::AzureAks => ComputeEnvironment::AzureKubernetes,
+            Self::AzureVM => ComputeEnvironment::AzureVM,
+            Self::GcpCloudRunGen1 => ComputeEnvironment::GcpCloudRunGen1,
+            Self::GcpCloudRunGen2 => ComputeEnvironment::GcpCloudRunGen2,
+            Self::GcpCloudRunJob => ComputeEnvironment::GcpCloudRunJob,
+            Self::GcpComputeEngine => ComputeEnvironment::GcpComputeEngine,
+            Self::GcpKubernetesEngine => ComputeEnvironment::GcpKubernetes,
+            Self::Custom(custom) => ComputeEnvironment::Custom(*custom),
+        }
+    }
+}
+
+impl Display for ManagedPlatform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Supported workload orchestrators that can be detected by this crate.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum Orchestrator {
+    /// Kubernetes
+    Kubernetes,
+    /// Nomad
+    Nomad,
+}
+
+impl Orchestrator {
+    /// Static str representation of the [`Orchestrator`].
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Kubernetes => "Kubernetes",
+            Self::Nomad => "Nomad",
+        }
+    }
+}
+
+impl Display for Orchestrator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Supported container runtimes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum ContainerRuntime {
+    /// Docker
+    Docker,
+    /// Podman
+    Podman,
+    /// containerd
+    Containerd,
+    /// CRI-O
+    #[cfg_attr(feature = "serde", serde(rename = "crio"))]
+    CriO,
+}
+
+impl ContainerRuntime {
+    /// Static str representation of the [`ContainerRuntime`].
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Docker => "Docker",
+            Self::Podman => "Podman",
+            Self::Containerd => "containerd",
+            Self::CriO => "CRI-O",
+        }
+    }
+}
+
+impl Display for ContainerRuntime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Supported hypervisors that can be detected by this crate.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum Hypervisor {
+    /// QEMU
+    Qemu,
+    /// KVM
+    Kvm,
+    /// Microsoft Hyper-V
+    #[cfg_attr(feature = "serde", serde(rename = "hyperv"))]
+    HyperV,
+    /// Xen
+    Xen,
+    /// VMware
+    #[cfg_attr(feature = "serde", serde(rename = "vmware"))]
+    VMware,
+    /// bhyve
+    Bhyve,
+    /// ACRN
+    Acrn,
+}
+
+impl Hypervisor {
+    /// Static str representation of the [`Hypervisor`].
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Qemu => "QEMU",
+            Self::Kvm => "KVM",
+            Self::HyperV => "Hyper-V",
+            Self::Xen => "Xen",
+            Self::VMware => "VMware",
+            Self::Bhyve => "bhyve",
+            Self::Acrn => "ACRN",
+        }
+    }
+}
+
+impl Display for Hypervisor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Supported CPU families that can be detected by this crate.
+///
+/// These are ARM server cores, which identify the CPU of cloud instances:
+/// AWS Graviton (Cortex-A72, Neoverse N1, V1 and V2), Ampere Altra (Neoverse N1), AmpereOne, Azure
+/// Cobalt 100 (Neoverse N2) and Google Axion (Neoverse V2).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum CpuFamily {
+    /// Arm Cortex-A72
+    CortexA72,
+    /// Arm Neoverse N1
+    NeoverseN1,
+    /// Arm Neoverse N2
+    NeoverseN2,
+    /// Arm Neoverse V1
+    NeoverseV1,
+    /// Arm Neoverse V2
+    NeoverseV2,
+    /// AmpereOne
+    AmpereOne,
+}
+
+impl CpuFamily {
+    /// Static str representation of the [`CpuFamily`].
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::CortexA72 => "Cortex-A72",
+            Self::NeoverseN1 => "Neoverse N1",
+            Self::NeoverseN2 => "Neoverse N2",
+            Self::NeoverseV1 => "Neoverse V1",
+            Self::NeoverseV2 => "Neoverse V2",
+            Self::AmpereOne => "AmpereOne",
+        }
+    }
+}
+
+impl Display for CpuFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, ops::Deref};
+
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{
+        detect_inner, env_vars,
+        smbios::{self, Smbios},
+        tests::detectors,
+        DetectionSnapshot, Detector, SmbiosPattern, MAX_INDIVIDUAL_WEIGHTING,
+    };
+
+    #[rstest]
+    fn test_compute_environment(
+        #[values(
+            ComputeEnvironment::AwsEc2,
+            ComputeEnvironment::AwsEcs,
+            ComputeEnvironment::AwsLambda,
+            ComputeEnvironment::AwsKubernetes,
+            ComputeEnvironment::AwsNomad,
+            ComputeEnvironment::AzureContainerApps,
+            ComputeEnvironment::AzureContainerAppsJob,
+            ComputeEnvironment::AzureContainerInstance,
+            ComputeEnvironment::AzureKubernetes,
+            ComputeEnvironment::AzureVM,
+            ComputeEnvironment::AzureNomad,
+            ComputeEnvironment::GcpCloudRunGen1,
+            ComputeEnvironment::GcpCloudRunGen2,
+            ComputeEnvironment::GcpCloudRunJob,
+            ComputeEnvironment::GcpComputeEngine,
+            ComputeEnvironment::GcpKubernetes,
+            ComputeEnvironment::GcpNomad,
+            ComputeEnvironment::Kubernetes,
+            ComputeEnvironment::Nomad,
+            ComputeEnvironment::Qemu
+        )]
+        environment: ComputeEnvironment,
+        detectors: Vec<Detector>,
+    ) {
+        let smbios: Smbios = environment.detector().smbios.clone().into();
+        let env_vars: HashSet<_> = environment
+            .detector()
+            .env_vars
+            .iter()
+            .map(Deref::deref)
+            .collect();
+
+        let report = detect_inner(
+            &detectors,
+            &DetectionSnapshot::new(smbios, env_vars),
+            u16::MIN,
+        );
+        let stack = EnvironmentStack::from(&report);
+
+        assert_eq!(Some(environment), stack.compute_environment());
+        assert_eq!(
+            environment.cloud_provider(),
+            stack.cloud_provider.map(|layer| layer.value)
+        );
+        assert_eq!(
+            environment.orchestrator(),
+            stack.orchestrator.map(|layer| layer.value)
+        );
+    }
+
+    #[rstest]
+    fn test_hypervisor_layer(mut detectors: Vec<Detector>) {
+        let environment =
+            ComputeEnvironment::Custom(CustomEnvironment::new("Custom Platform", "custom"));
+        detectors.push(Detector::new(
+            environment,
+            SmbiosPattern::new().with_sys_vendor("qemu"),
+            &["CUSTOM_ENV"],
+        ));
+        let smbios = Smbios::from(SmbiosPattern::new().with_sys_vendor("qemu"));
+        let env_vars = HashSet::from(["CUSTOM_ENV"]);
+
+        let report = detect_inner(
+            &detectors,
+            &DetectionSnapshot::new(smbios, env_vars),
+            MAX_INDIVIDUAL_WEIGHTING,
+        );
+        let stack = EnvironmentStack::from(&report);
+
+        assert_eq!(
+            Some(ManagedPlatform::Custom(CustomEnvironment::new(
+                "Custom Platform",
+                "custom"
+            ))),
+            stack.platform.map(|layer| layer.value)
+        );
+        assert_eq!(
+            Some(Hypervisor::Qemu),
+            stack.hypervisor.map(|layer| layer.value)
+        );
+        assert!(stack.hypervisor.unwrap().confidence < stack.platform.unwrap().confidence);
+        assert_eq!(Some(environment), stack.compute_environment());
+    }
+
+    #[rstest]
+    fn test_layer_confidence(detectors: Vec<Detector>) {
+        // The SMBIOS data of EKS nodes, with only half of the Kubernetes environment variables.
+        let env_vars = &env_vars::KUBERNETES[..env_vars::KUBERNETES.len() / 2];
+        let snapshot = DetectionSnapshot::new(smbios::AWS.into(), env_vars.iter().copied())
+            .with_signals([Signal::ContainerRuntime(ContainerRuntime::Containerd)]);
+
+        let report = detect_inner(&detectors, &snapshot, MAX_INDIVIDUAL_WEIGHTING);
+        let stack = EnvironmentStack::from(&report);
+
+        assert_eq!(
+            Some(ComputeEnvironment::AwsKubernetes),
+            stack.compute_environment()
+        );
+        let confidence = |layer: Option<u16>| layer.unwrap();
+        assert_eq!(
+            MAX_INDIVIDUAL_WEIGHTING,
+            confidence(stack.cloud_provider.map(|layer| layer.confidence))
+        );
+        assert_eq!(
+            MAX_INDIVIDUAL_WEIGHTING / 2,
+            confidence(stack.orchestrator.map(|layer| layer.confidence))
+        );
+        assert_eq!(
+            MAX_INDIVIDUAL_WEIGHTING + MAX_INDIVIDUAL_WEIGHTING / 2,
+            confidence(stack.platform.map(|layer| layer.confidence))
+        );
+        assert_eq!(
+            Signal::ContainerRuntime(ContainerRuntime::Containerd).weight(),
+            confidence(stack.container_runtime.map(|layer| layer.confidence))
+        );
+    }
+
+    #[rstest]
+    fn test_cloud_provider_layer_from_env_vars(detectors: Vec<Detector>) {
+        let snapshot =
+            DetectionSnapshot::new(Smbios::default(), env_vars::AWS_LAMBDA.iter().copied());
+
+        let report = detect_inner(&detectors, &snapshot, MAX_INDIVIDUAL_WEIGHTING);
+        let stack = EnvironmentStack::from(&report);
+
+        assert_eq!(
+            Some(ComputeEnvironment::AwsLambda),
+            stack.compute_environment()
+        );
+        assert_eq!(
+            Some(MAX_INDIVIDUAL_WEIGHTING),
+            stack.cloud_provider.map(|layer| layer.confidence)
+        );
+    }
+
+    #[rstest]
+    #[case::aws_ec2(smbios::AWS, Some(ComputeEnvironment::AwsEc2))]
+    #[case::none(SmbiosPattern::new(), None)]
+    fn test_container_runtime_layer(
+        #[case] smbios_pattern: SmbiosPattern,
+        #[case] expected: Option<ComputeEnvironment>,
+        detectors: Vec<Detector>,
+    ) {
+        let snapshot = DetectionSnapshot::new(Smbios::from(smbios_pattern), [])
+            .with_signals([Signal::ContainerRuntime(ContainerRuntime::Docker)]);
+
+        let report = detect_inner(&detectors, &snapshot, MAX_INDIVIDUAL_WEIGHTING);
+        let stack = EnvironmentStack::from(&report);
+
+        assert_eq!(expected, stack.compute_environment());
+        assert_eq!(
+            Some(ContainerRuntime::Docker),
+            stack.container_runtime.map(|layer| layer.value)
+        );
+    }
+
+    #[rstest]
+    #[case::aws_ec2(smbios::AWS, Some(ComputeEnvironment::AwsEc2), Hypervisor::Kvm)]
+    #[case::qemu(smbios::QEMU, Some(ComputeEnvironment::Qemu), Hypervisor::Qemu)]
+    #[case::none(SmbiosPattern::new(), None, Hypervisor::Kvm)]
+    fn test_observed_hypervisor_layer(
+        #[case] smbios_pattern: SmbiosPattern,
+        #[case] expected: Option<ComputeEnvironment>,
+        #[case] expected_hypervisor: Hypervisor,
+        detectors: Vec<Detector>,
+    ) {
+        let snapshot = DetectionSnapshot::new(Smbios::from(smbios_pattern), [])
+            .with_signals([Signal::Hypervisor(Hypervisor::Kvm)]);
+
+        let report = detect_inner(&detectors, &snapshot, MAX_INDIVIDUAL_WEIGHTING);
+        let stack = EnvironmentStack::from(&report);
+
+        assert_eq!(expected, stack.compute_environment());
+        assert_eq!(
+            Some(expected_hypervisor),
+            stack.hypervisor.map(|layer| layer.value)
+        );
+    }
+
+    #[rstest]
+    #[case::aws_ec2(smbios::AWS, Some(ComputeEnvironment::AwsEc2))]
+    #[case::none(SmbiosPattern::new(), None)]
+    fn test_cpu_family_layer(
+        #[case] smbios_pattern: SmbiosPattern,
+        #[case] expected: Option<ComputeEnvironment>,
+        detectors: Vec<Detector>,
+    ) {
+        let snapshot = DetectionSnapshot::new(Smbios::from(smbios_pattern), [])
+            .with_signals([Signal::Cpu(CpuFamily::NeoverseN1)]);
+
+        let report = detect_inner(&detectors, &snapshot, MAX_INDIVIDUAL_WEIGHTING);
+        let stack = EnvironmentStack::from(&report);
+
+        assert_eq!(expected, stack.compute_environment());
+        assert_eq!(
+            Some(CpuFamily::NeoverseN1),
+            stack.cpu_family.map(|layer| layer.value)
+        );
+    }
+
+    #[rstest]
+    fn test_empty() {
+        assert_eq!(None, EnvironmentStack::default().compute_environment());
+    }
+}